@@ -1,26 +1,49 @@
 mod modules;
-use modules::tokenizer::tokenize;
-use modules::parser::Parser;
-use modules::interprete::evaluate;
+pub use modules::tokenizer::{tokenize, try_tokenize, Token};
+pub use modules::parser::{Expr, Parser, Pattern, Schema};
+pub use modules::error::ExprError;
+pub use modules::interprete::{
+    evaluate, evaluate_program, evaluate_with, try_evaluate, try_evaluate_program, try_evaluate_with, Context, EvalResult,
+};
+pub use modules::functions::{signature, ValueType};
+pub use modules::decimal::Rounding;
+pub use rust_decimal::Decimal;
+pub use modules::template::{render, Template};
 
+// Ejecuta un programa (sentencias separadas por ';') y devuelve el valor de la última
 pub fn execute(expresion: String) -> Result<String, String> {
-    let tokens = tokenize(&expresion);
-    let mut parser = Parser::new(&tokens);
-    match parser.parse() {
-        Some(parsed_expr) =>{
-            let result = evaluate(parsed_expr);
-            match result {
-                modules::interprete::EvalResult::Bool(b) => Ok(b.to_string()),
-                modules::interprete::EvalResult::String(s) => Ok(s.to_string()),
-            }
-        },
-        None => Err("Error: Not valid expresion".to_string()),
+    execute_with(expresion, &mut Context::new())
+}
+
+// Igual que execute, pero con las variables del contexto disponibles. Un programa que solo define
+// funciones o importa módulos (un preludio) no produce valores y da null.
+pub fn execute_with(expresion: String, context: &mut Context) -> Result<String, String> {
+    let mut results = execute_all_with(expresion, context)?;
+    Ok(results.pop().unwrap_or_else(|| to_output(EvalResult::Null)))
+}
+
+// Ejecuta un programa y devuelve el valor de cada una de sus sentencias
+pub fn execute_all(expresion: String) -> Result<Vec<String>, String> {
+    execute_all_with(expresion, &mut Context::new())
+}
+
+pub fn execute_all_with(expresion: String, context: &mut Context) -> Result<Vec<String>, String> {
+    let tokens = try_tokenize(&expresion).map_err(|err| err.to_string())?;
+    let statements = Parser::new(&tokens).try_parse_program().map_err(|err| err.to_string())?;
+    if statements.is_empty() {
+        return Err("Error: Not valid expresion".to_string());
     }
+    let results = try_evaluate_program(statements, context).map_err(|err| err.to_string())?;
+    Ok(results.into_iter().map(to_output).collect())
+}
+
+fn to_output(result: EvalResult) -> String {
+    result.to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::execute;
+    use crate::{execute, execute_all, execute_with, Context, EvalResult};
 
     #[test]
     fn test_complex_logic() {
@@ -33,4 +56,153 @@ mod tests {
         let input = r#"((true && "un coala \"pepe\"" != "texto") || true) && !("uno"!="dos" || true) && !("helloworld"=="camel" && false)"#.to_string();
         assert_eq!(execute(input).unwrap(), "false");
     }
+
+    #[test]
+    fn test_case_when_with_context() {
+        let mut context = Context::new();
+        context.set("x", EvalResult::Int(42));
+        let input = r#"case when x > 10 then "big" when x > 5 then "mid" else "small" end"#.to_string();
+        assert_eq!(execute_with(input, &mut context).unwrap(), "big");
+    }
+
+    #[test]
+    fn test_program_final_value() {
+        let input = "rate = 0.5; discount = 40 * rate; discount == 20".to_string();
+        assert_eq!(execute(input).unwrap(), "true");
+    }
+
+    #[test]
+    fn test_program_all_values() {
+        let input = r#"a = 2; b = a * 3; a < b ? "menor" : "mayor";"#.to_string();
+        assert_eq!(execute_all(input).unwrap(), vec!["2", "6", "menor"]);
+    }
+
+    #[test]
+    fn test_decimal_prices() {
+        let input = "price = 19.99d; total = price * 3; total == 59.97d ? \"${total} €\" : \"error\"".to_string();
+        assert_eq!(execute(input).unwrap(), "59.97 €");
+        assert_eq!(execute("0.1d + 0.2d == 0.3d".to_string()).unwrap(), "true");
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(execute("0.1 + 0.2 == 0.3".to_string()).unwrap(), "false");
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        assert_eq!(execute("0xFF + 0b1010 + 0o10".to_string()).unwrap(), "273");
+        assert_eq!(execute("1_000_000 * 15%".to_string()).unwrap(), "150000");
+        assert_eq!(execute("10% 3".to_string()).unwrap(), "1");
+        // Tras "%" un "-" empieza el divisor, con o sin espacios y sea el dividendo un literal o no
+        for input in ["7%-2", "7 % -2", "x = 7; x%-2", "x = 7; x % -2"] {
+            assert_eq!(execute(input.to_string()).unwrap(), "-1", "{}", input);
+        }
+        assert_eq!(execute("(15%) - 2 == -1.85".to_string()).unwrap(), "true");
+        assert_eq!(execute("x = 20; x% * 50".to_string()).unwrap(), "10");
+        assert_eq!(execute("if true then 5% * 100 else 10%".to_string()).unwrap(), "5");
+        assert_eq!(execute("case when false then 5% else 10% * 100 end".to_string()).unwrap(), "10");
+        assert_eq!(execute("let x = 5% in x * 100".to_string()).unwrap(), "5");
+        assert_eq!(execute("2.5e3 == 2500".to_string()).unwrap(), "true");
+        assert_eq!(execute("-9223372036854775808".to_string()).unwrap(), "-9223372036854775808");
+        assert_eq!(execute("-9223372036854775808 == -9223372036854775807 - 1".to_string()).unwrap(), "true");
+    }
+
+    #[test]
+    #[cfg(feature = "bignum")]
+    fn test_exact_numbers_print_as_decimals() {
+        assert_eq!(execute("0.5".to_string()).unwrap(), "0.5");
+        assert_eq!(execute("19.99 * 3".to_string()).unwrap(), "59.97");
+        assert_eq!(execute("15%".to_string()).unwrap(), "0.15");
+        assert_eq!(execute("avg([1, 2])".to_string()).unwrap(), "1.5");
+        assert_eq!(execute(r#""Total: ${0.1 + 0.2} €""#.to_string()).unwrap(), "Total: 0.3 €");
+        assert_eq!(execute("2 / 3".to_string()).unwrap(), "0.6666666666666666666666666667");
+        assert_eq!(execute("1 - 1e-400".to_string()).unwrap(), "1");
+        // La fracción exacta solo si se pide
+        assert_eq!(execute("fraction(2 / 3)".to_string()).unwrap(), "2/3");
+    }
+
+    #[test]
+    #[cfg(feature = "math")]
+    fn test_math_functions() {
+        assert_eq!(execute("r = 2; round(PI * r ** 2, 2)".to_string()).unwrap(), "12.57");
+        assert_eq!(execute("clamp(sqrt(16) + abs(-3), 0, 5)".to_string()).unwrap(), "5");
+        // Una variable del contexto oculta la constante
+        assert_eq!(execute("E = 1; E + 1".to_string()).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_statistics_rules() {
+        let mut context = Context::new();
+        context.set("latencies", EvalResult::List([180, 210, 250, 190].iter().map(|l| EvalResult::Int(*l)).collect()));
+        assert_eq!(execute_with("avg(latencies) > 200".to_string(), &mut context).unwrap(), "true");
+        assert_eq!(execute_with("percentile(latencies, 50) == median(latencies)".to_string(), &mut context).unwrap(), "true");
+    }
+
+    #[test]
+    fn test_financial_formulas() {
+        assert_eq!(execute("round(pmt(8% / 12, 10, 10000), 2)".to_string()).unwrap(), "-1037.03");
+        assert_eq!(execute("round(npv(10%, [-10000, 3000, 4200, 6800]), 2)".to_string()).unwrap(), "1188.44");
+    }
+
+    #[test]
+    fn test_runtime_errors_are_returned() {
+        assert_eq!(execute("x = 0; 10 / x".to_string()), Err("Error: División entre cero".to_string()));
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(execute("9223372036854775807 * 2".to_string()).unwrap_err(), "Error: Desbordamiento de entero en 9223372036854775807 * 2");
+    }
+
+    #[test]
+    fn test_lexer_errors_are_returned() {
+        assert_eq!(execute("1.2.3".to_string()), Err("Error: Número mal formado: 1.2.3".to_string()));
+        assert_eq!(execute("0xZZ + 1".to_string()), Err("Error: Número mal formado: 0xZZ".to_string()));
+        assert_eq!(execute(r#""${1.2.3}""#.to_string()), Err("Error: Número mal formado: 1.2.3".to_string()));
+    }
+
+    #[test]
+    fn test_import_errors_are_returned() {
+        let mut context = Context::new();
+        context.register_module("a", r#"import "b";"#);
+        context.register_module("b", r#"import "a";"#);
+        assert_eq!(
+            execute_with(r#"import "a"; 1"#.to_string(), &mut context),
+            Err("Error: Importación cíclica: a -> b -> a".to_string())
+        );
+        assert!(execute_with(r#"import "pricing"; 1"#.to_string(), &mut context).is_err());
+    }
+
+    #[test]
+    fn test_empty_program() {
+        assert!(execute("".to_string()).is_err());
+        assert_eq!(execute(";".to_string()), Err("Error: Not valid expresion".to_string()));
+        // Solo definiciones: no hay valor que devolver
+        assert_eq!(execute("fn f() = 1".to_string()).unwrap(), "null");
+        assert_eq!(execute_all("fn f() = 1; fn g() = 2".to_string()).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_syntax_errors_are_returned() {
+        assert_eq!(execute("(1 + 2".to_string()), Err("Error: No se encontró un paréntesis de cierre".to_string()));
+        assert_eq!(execute("if true then 1".to_string()), Err("Error: Se esperaba 'else' en la expresión if".to_string()));
+        assert_eq!(execute("x = 1 +".to_string()), Err("Error: Not valid expresion".to_string()));
+        assert!(execute(r#"matches("x", "(")"#.to_string()).unwrap_err().starts_with("Error: Expresión regular no válida"));
+    }
+
+    #[test]
+    fn test_prelude_shared_by_rules() {
+        let mut context = Context::new();
+        let prelude = "fn is_adult(a) = a >= 18; fn countdown(n) = n == 0 ? 0 : countdown(n - 1);".to_string();
+        assert_eq!(execute_with(prelude, &mut context).unwrap(), "null");
+
+        context.set("age", EvalResult::Int(30));
+        assert_eq!(execute_with("is_adult(age)".to_string(), &mut context).unwrap(), "true");
+        assert_eq!(execute_with("is_adult(age / 2)".to_string(), &mut context).unwrap(), "false");
+        assert_eq!(execute_with("countdown(60)".to_string(), &mut context).unwrap(), "0");
+    }
+
+    #[test]
+    fn test_list_field_rules() {
+        let mut context = Context::new();
+        let prices = [40.0, 150.0, 90.0].iter().map(|p| EvalResult::Float(*p)).collect();
+        context.set("prices", EvalResult::List(prices));
+        assert_eq!(execute_with("any(prices, p -> p > 100)".to_string(), &mut context).unwrap(), "true");
+        assert_eq!(execute_with("map(prices, p -> p * 2)".to_string(), &mut context).unwrap(), "[80, 300, 180]");
+    }
 }
@@ -0,0 +1,240 @@
+use crate::modules::decimal::{round, Rounding};
+use crate::modules::error::{fail, ExprError};
+use crate::modules::interprete::EvalResult;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+// Números exactos (característica "bignum"): enteros de cualquier tamaño y fracciones. Un
+// resultado que es un entero y cabe en un i64 vuelve a ser un Int, así que EvalResult::Rational
+// solo contiene enteros grandes y fracciones.
+
+const MAX_EXPONENT: i32 = 10_000;
+const MAX_POWER_BITS: u64 = 1_000_000;
+// Cifras decimales con las que se muestra una fracción (las mismas que admite un Decimal)
+const DISPLAY_DECIMALS: usize = 28;
+
+// Literal numérico del código: "0.1" es 1/10, "1e-3" es 1/1000 y "123456789012345678901234567890" no se desborda.
+// None si el exponente es tan grande que el número no se puede representar en memoria (1e999999999).
+pub fn parse_literal(text: &str) -> Option<BigRational> {
+    let (mantissa, exponent) = text.split_once(['e', 'E']).unwrap_or((text, "0"));
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: BigInt = format!("{}{}", integer, fraction).parse().unwrap_or_default();
+    let exponent = match exponent.parse::<i32>() {
+        Ok(exponent) if exponent.abs() <= MAX_EXPONENT => exponent - fraction.len() as i32,
+        _ => return None,
+    };
+    let scale = BigInt::from(10).pow(exponent.unsigned_abs());
+    match exponent >= 0 {
+        true => Some(BigRational::from_integer(digits * scale)),
+        false => Some(BigRational::new(digits, scale)),
+    }
+}
+
+pub fn from_int(n: i64) -> BigRational {
+    BigRational::from_integer(BigInt::from(n))
+}
+
+pub fn from_decimal(n: Decimal) -> BigRational {
+    BigRational::new(BigInt::from(n.mantissa()), BigInt::from(10).pow(n.scale()))
+}
+
+pub fn to_float(n: &BigRational) -> Option<f64> {
+    n.to_f64()
+}
+
+pub fn is_zero(n: &BigRational) -> bool {
+    n.is_zero()
+}
+
+// Forma canónica de un resultado exacto
+pub fn normalize(n: BigRational) -> EvalResult {
+    match n.is_integer().then(|| n.to_integer().to_i64()).flatten() {
+        Some(integer) => EvalResult::Int(integer),
+        None => EvalResult::Rational(n),
+    }
+}
+
+// Enteros, decimales exactos y fracciones se pueden operar sin perder precisión; un f64 no
+fn to_rational(value: &EvalResult) -> Option<BigRational> {
+    match value {
+        EvalResult::Int(n) => Some(from_int(*n)),
+        EvalResult::Decimal(n) => Some(from_decimal(*n)),
+        EvalResult::Rational(n) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+// Operandos exactos de una operación en la que interviene una fracción (o de una división
+// entre enteros, si "integers" es true); None si la operación no es exacta
+pub fn exact_operands(left: &EvalResult, right: &EvalResult, integers: bool) -> Option<(BigRational, BigRational)> {
+    let involved = matches!(left, EvalResult::Rational(_))
+        || matches!(right, EvalResult::Rational(_))
+        || (integers && matches!((left, right), (EvalResult::Int(_), EvalResult::Int(_))));
+    match involved {
+        true => Some((to_rational(left)?, to_rational(right)?)),
+        false => None,
+    }
+}
+
+pub fn operate(op: &str, left: BigRational, right: BigRational) -> Result<EvalResult, ExprError> {
+    let result = match op {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" | "//" | "%" if right.is_zero() => return Err(ExprError::DivisionByZero),
+        "/" => left / right,
+        // Cociente y resto redondeando hacia -infinito, igual que con los enteros
+        "//" => (left / right).floor(),
+        "%" => left.clone() - right.clone() * (left / right).floor(),
+        _ => fail!("Operación no soportada con números exactos: {}", op),
+    };
+    Ok(normalize(result))
+}
+
+// Potencia exacta; con exponente negativo es la fracción inversa (2 ** -1 es 1/2).
+// El resultado se limita para que un exponente enorme no agote la memoria.
+pub fn power(base: BigRational, exponent: i64) -> Result<EvalResult, ExprError> {
+    if base.is_zero() {
+        return match exponent.cmp(&0) {
+            Ordering::Less => Err(ExprError::DivisionByZero),
+            Ordering::Equal => Ok(EvalResult::Int(1)),
+            Ordering::Greater => Ok(EvalResult::Int(0)),
+        };
+    }
+    let bits = base.numer().bits().max(base.denom().bits()) - 1;
+    if bits.saturating_mul(exponent.unsigned_abs()) > MAX_POWER_BITS {
+        return Err(ExprError::Overflow(format!("{} ** {}", base, exponent)));
+    }
+    // Solo una base 1 o -1 admite exponentes enormes, y de ellos solo importa la paridad
+    let exponent = i32::try_from(exponent).unwrap_or((exponent % 2) as i32);
+    Ok(normalize(base.pow(exponent)))
+}
+
+// Valor de una fracción como decimal exacto, con 28 cifras significativas como máximo
+pub fn to_decimal(n: &BigRational) -> Option<Decimal> {
+    let numer = Decimal::from_i128_with_scale(n.numer().to_i128()?, 0);
+    let denom = Decimal::from_i128_with_scale(n.denom().to_i128()?, 0);
+    numer.checked_div(denom)
+}
+
+// Texto de una fracción en notación decimal, como se muestran los demás números: 1/2 es 0.5 y
+// 5997/100 es 59.97. Si no tiene una expresión decimal finita de como mucho 28 cifras (1/3), se
+// muestra redondeada a 28 cifras significativas; la fracción exacta se obtiene con fraction(x).
+pub fn format(n: &BigRational) -> String {
+    if n.is_integer() {
+        return n.to_integer().to_string();
+    }
+    let sign = if n.is_negative() { "-" } else { "" };
+    let n = n.abs();
+    match decimal_places(n.denom()) {
+        Some(places) if places <= DISPLAY_DECIMALS => format!("{}{}", sign, fixed(&n, places)),
+        _ => format!("{}{}", sign, approximate(&n)),
+    }
+}
+
+// Cifras decimales de una fracción cuyo denominador solo tiene los factores 2 y 5; None si no es así
+fn decimal_places(denom: &BigInt) -> Option<usize> {
+    let (mut denom, mut twos, mut fives) = (denom.clone(), 0, 0);
+    let (two, five) = (BigInt::from(2), BigInt::from(5));
+    while (&denom % &two).is_zero() {
+        denom /= &two;
+        twos += 1;
+    }
+    while (&denom % &five).is_zero() {
+        denom /= &five;
+        fives += 1;
+    }
+    (denom == BigInt::from(1)).then_some(twos.max(fives))
+}
+
+// Fracción positiva redondeada a "places" decimales, sin ceros finales
+fn fixed(n: &BigRational, places: usize) -> String {
+    let scale = BigRational::from_integer(BigInt::from(10).pow(places as u32));
+    let digits = format!("{:0>width$}", (n * scale).round().to_integer(), width = places + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - places);
+    match fraction.trim_end_matches('0') {
+        "" => integer.to_string(),
+        fraction => format!("{}.{}", integer, fraction),
+    }
+}
+
+// Fracción positiva con 28 cifras significativas; las muy pequeñas o muy grandes con exponente (1e-400)
+fn approximate(n: &BigRational) -> String {
+    let mut exponent = n.numer().to_string().len() as i64 - n.denom().to_string().len() as i64;
+    let power = |exponent: i64| BigRational::from_integer(BigInt::from(10).pow(exponent.unsigned_abs() as u32));
+    let scaled = |exponent: i64| match exponent >= 0 {
+        true => n / power(exponent),
+        false => n * power(exponent),
+    };
+    // Ajusta el exponente para que la mantisa quede entre 1 y 10
+    if scaled(exponent) < BigRational::from_integer(BigInt::from(1)) {
+        exponent -= 1;
+    }
+    if (-(DISPLAY_DECIMALS as i64)..DISPLAY_DECIMALS as i64).contains(&exponent) {
+        let places = (DISPLAY_DECIMALS as i64 - 1 - exponent).max(0) as usize;
+        return fixed(n, places);
+    }
+    format!("{}e{}", fixed(&scaled(exponent), DISPLAY_DECIMALS - 1), exponent)
+}
+
+// round() de una fracción: se redondea como un decimal exacto (round(2/3, 2) es 0.67).
+// Un entero grande no tiene decimales que redondear.
+pub fn round_rational(n: BigRational, scale: u32, rounding: Rounding) -> Result<EvalResult, ExprError> {
+    if n.is_integer() {
+        return Ok(normalize(n));
+    }
+    match to_decimal(&n) {
+        Some(decimal) => Ok(EvalResult::Decimal(round(decimal, scale, rounding))),
+        None => fail!("No se puede redondear {}: no cabe en un decimal", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(parse_literal("0.1").unwrap(), fraction(1, 10));
+        assert_eq!(parse_literal("2.50").unwrap(), fraction(5, 2));
+        assert_eq!(parse_literal(".5").unwrap(), fraction(1, 2));
+        assert_eq!(parse_literal("2.5e3").unwrap(), fraction(2500, 1));
+        assert_eq!(parse_literal("1e-3").unwrap(), fraction(1, 1000));
+        assert_eq!(parse_literal("18446744073709551616").unwrap().to_string(), "18446744073709551616");
+        assert_eq!(parse_literal("1e999999999"), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(fraction(4, 2)), EvalResult::Int(2));
+        assert_eq!(normalize(fraction(1, 3)), EvalResult::Rational(fraction(1, 3)));
+        assert_eq!(normalize(from_int(i64::MAX) + from_int(1)).to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(&fraction(1, 2)), "0.5");
+        assert_eq!(format(&fraction(5997, 100)), "59.97");
+        assert_eq!(format(&fraction(-3, 20)), "-0.15");
+        assert_eq!(format(&fraction(1, 3)), "0.3333333333333333333333333333");
+        assert_eq!(format(&fraction(-2, 3)), "-0.6666666666666666666666666667");
+        assert_eq!(format(&fraction(200, 3)), "66.66666666666666666666666667");
+        assert_eq!(format(&parse_literal("1e-400").unwrap()), "1e-400");
+        assert_eq!(format(&(from_int(1) - parse_literal("1e-400").unwrap())), "1");
+        assert_eq!(format(&(parse_literal("1e40").unwrap() / from_int(3))), "3.333333333333333333333333333e39");
+    }
+
+    #[test]
+    fn test_round_rational() {
+        let rounded = round_rational(fraction(2, 3), 2, Rounding::HalfEven).unwrap();
+        assert_eq!(rounded, EvalResult::Decimal(Decimal::new(67, 2)));
+        assert_eq!(round_rational(fraction(5, 1), 2, Rounding::HalfEven).unwrap(), EvalResult::Int(5));
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(feature = "collation")]
+use crate::modules::error::{fail, ExprError};
+use caseless::compatibility_caseless_match_str;
+#[cfg(feature = "collation")]
+use icu_collator::{Collator, CollatorOptions};
+#[cfg(feature = "collation")]
+use icu_locid::Locale;
+#[cfg(feature = "collation")]
+use std::cmp::Ordering;
+use unicode_normalization::UnicodeNormalization;
+
+// Igualdad sin distinguir mayúsculas ni formas Unicode equivalentes: normaliza con NFKC y
+// aplica el plegado de mayúsculas, así "Café" compuesto y descompuesto, "ESPAÑA" y "españa"
+// o "ﬁ" y "fi" son iguales
+pub fn caseless_eq(left: &str, right: &str) -> bool {
+    compatibility_caseless_match_str(left, right)
+}
+
+// Normaliza un texto a la forma indicada: NFC, NFD, NFKC o NFKD; None si la forma no existe
+pub fn normalize(text: &str, form: &str) -> Option<String> {
+    match form.to_uppercase().as_str() {
+        "NFC" => Some(text.nfc().collect()),
+        "NFD" => Some(text.nfd().collect()),
+        "NFKC" => Some(text.nfkc().collect()),
+        "NFKD" => Some(text.nfkd().collect()),
+        _ => None,
+    }
+}
+
+// Orden alfabético de los textos según las reglas de un idioma (colación de ICU)
+#[cfg(feature = "collation")]
+pub struct Collation {
+    collator: Collator,
+}
+
+#[cfg(feature = "collation")]
+impl Collation {
+    // "" es el orden raíz de Unicode, común a todos los idiomas
+    pub fn new(locale: &str) -> Result<Self, ExprError> {
+        let parsed: Locale = match locale {
+            "" => Locale::UND,
+            locale => match locale.parse() {
+                Ok(parsed) => parsed,
+                Err(err) => fail!("Idioma no válido \"{}\": {}", locale, err),
+            },
+        };
+        match Collator::try_new(&(&parsed).into(), CollatorOptions::new()) {
+            Ok(collator) => Ok(Collation { collator }),
+            Err(err) => fail!("No hay reglas de ordenación para \"{}\": {}", locale, err),
+        }
+    }
+
+    pub fn compare(&self, left: &str, right: &str) -> Ordering {
+        self.collator.compare(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caseless_eq() {
+        assert!(caseless_eq("Caf\u{e9}", "CAFE\u{301}"));
+        assert!(caseless_eq("ESPAÑA", "españa"));
+        assert!(caseless_eq("Straße", "STRASSE"));
+        assert!(caseless_eq("\u{fb01}n", "FIN"));
+        assert!(!caseless_eq("españa", "espana"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Cafe\u{301}", "nfc").unwrap(), "Caf\u{e9}");
+        assert_eq!(normalize("Caf\u{e9}", "NFD").unwrap(), "Cafe\u{301}");
+        assert_eq!(normalize("\u{fb01}", "NFKC").unwrap(), "fi");
+        assert_eq!(normalize("a", "bogus"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "collation")]
+    fn test_collation_by_locale() {
+        // En el orden raíz la ñ va entre la n y la o, no después de la z como en los bytes
+        let root = Collation::new("").unwrap();
+        assert_eq!(root.compare("ñu", "oso"), Ordering::Less);
+        assert_eq!(root.compare("árbol", "burro"), Ordering::Less);
+        // En sueco la ä va después de la z
+        let swedish = Collation::new("sv").unwrap();
+        assert_eq!(swedish.compare("ära", "zeta"), Ordering::Greater);
+        assert_eq!(root.compare("ära", "zeta"), Ordering::Less);
+    }
+
+    #[test]
+    #[cfg(feature = "collation")]
+    fn test_invalid_locale() {
+        let error = Collation::new("no es un idioma").err().unwrap();
+        assert!(error.to_string().starts_with("Error: Idioma no válido \"no es un idioma\""), "{}", error);
+    }
+}
@@ -0,0 +1,100 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+// Modo de redondeo de los decimales exactos, con los nombres que se usan en round(x, 2, "half_even")
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    HalfEven, // al par más cercano en caso de empate (redondeo bancario)
+    HalfUp,   // el empate se aleja del cero: 2.5 -> 3, -2.5 -> -3
+    HalfDown, // el empate se acerca al cero: 2.5 -> 2
+    Up,       // siempre se aleja del cero
+    Down,     // siempre se acerca al cero (trunca)
+    Ceiling,  // hacia +infinito
+    Floor,    // hacia -infinito
+}
+
+impl Rounding {
+    pub fn from_name(name: &str) -> Option<Rounding> {
+        match name {
+            "half_even" => Some(Rounding::HalfEven),
+            "half_up" => Some(Rounding::HalfUp),
+            "half_down" => Some(Rounding::HalfDown),
+            "up" => Some(Rounding::Up),
+            "down" => Some(Rounding::Down),
+            "ceiling" => Some(Rounding::Ceiling),
+            "floor" => Some(Rounding::Floor),
+            _ => None,
+        }
+    }
+
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::HalfDown => RoundingStrategy::MidpointTowardZero,
+            Rounding::Up => RoundingStrategy::AwayFromZero,
+            Rounding::Down => RoundingStrategy::ToZero,
+            Rounding::Ceiling => RoundingStrategy::ToPositiveInfinity,
+            Rounding::Floor => RoundingStrategy::ToNegativeInfinity,
+        }
+    }
+}
+
+// Redondea a "scale" cifras decimales; el resultado conserva esa escala (round(2.5d, 2) es 2.50)
+pub fn round(value: Decimal, scale: u32, rounding: Rounding) -> Decimal {
+    let mut rounded = value.round_dp_with_strategy(scale, rounding.strategy());
+    rounded.rescale(scale);
+    rounded
+}
+
+// Redondeo de un f64 pasando por un decimal exacto, para que 2.675 se trate como el literal que
+// se escribió y no como 2.67499999... Los valores que no caben en un Decimal ya son enteros.
+pub fn round_float(value: f64, scale: u32, rounding: Rounding) -> f64 {
+    match Decimal::try_from(value) {
+        Ok(decimal) => f64::try_from(round(decimal, scale, rounding)).unwrap_or(value),
+        Err(_) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let cases = [
+            ("half_even", ["2", "2", "-2", "3"]),
+            ("half_up", ["3", "2", "-3", "3"]),
+            ("half_down", ["2", "2", "-2", "3"]),
+            ("up", ["3", "3", "-3", "3"]),
+            ("down", ["2", "2", "-2", "2"]),
+            ("ceiling", ["3", "3", "-2", "3"]),
+            ("floor", ["2", "2", "-3", "2"]),
+        ];
+        for (name, expected) in cases {
+            let rounding = Rounding::from_name(name).unwrap();
+            let rounded: Vec<String> =
+                ["2.5", "2.1", "-2.5", "2.6"].iter().map(|v| round(dec(v), 0, rounding).to_string()).collect();
+            assert_eq!(rounded, expected, "{}", name);
+        }
+        assert_eq!(Rounding::from_name("bankers"), None);
+    }
+
+    #[test]
+    fn test_round_keeps_scale() {
+        assert_eq!(round(dec("19.999"), 2, Rounding::HalfEven).to_string(), "20.00");
+        assert_eq!(round(dec("2.5"), 2, Rounding::HalfEven).to_string(), "2.50");
+        assert_eq!(round(dec("0.125"), 2, Rounding::HalfEven).to_string(), "0.12");
+    }
+
+    #[test]
+    fn test_round_float() {
+        assert_eq!(round_float(2.675, 2, Rounding::HalfUp), 2.68);
+        assert_eq!(round_float(0.125, 2, Rounding::HalfEven), 0.12);
+        assert_eq!(round_float(1e300, 2, Rounding::HalfUp), 1e300);
+    }
+}
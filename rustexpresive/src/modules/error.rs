@@ -0,0 +1,39 @@
+use std::fmt;
+
+// Errores que se producen al leer o al evaluar una expresión
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    // División o resto entre cero, tanto con enteros como con decimales
+    DivisionByZero,
+    // El resultado de una operación entera no cabe en un i64, por ejemplo "9223372036854775807 + 1"
+    Overflow(String),
+    // El resultado de una operación con decimales sería NaN o infinito, por ejemplo "1e308 * 10"
+    NotFinite(String),
+    // Tipos incorrectos, variables o funciones que no existen, índices fuera de rango...
+    Runtime(String),
+    // Código que no se puede leer: números mal formados (1.2.3), cadenas sin cerrar, caracteres inesperados
+    Syntax(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExprError::DivisionByZero => write!(f, "Error: División entre cero"),
+            ExprError::Overflow(operation) => write!(f, "Error: Desbordamiento de entero en {}", operation),
+            ExprError::NotFinite(operation) => write!(f, "Error: Resultado no finito en {}", operation),
+            ExprError::Runtime(message) => write!(f, "Error: {}", message),
+            ExprError::Syntax(message) => write!(f, "Error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+// Termina la evaluación con un ExprError::Runtime con el mensaje indicado
+macro_rules! fail {
+    ($($arg:tt)*) => {
+        return Err($crate::modules::error::ExprError::Runtime(format!($($arg)*)))
+    };
+}
+
+pub(crate) use fail;
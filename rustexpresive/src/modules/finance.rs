@@ -0,0 +1,255 @@
+use crate::modules::error::{fail, ExprError};
+use crate::modules::functions::ValueType::{Float, List, Number};
+use crate::modules::functions::{list_arg, Builtin};
+use crate::modules::interprete::{finite, Context, EvalResult};
+
+// Funciones financieras con la semántica de las hojas de cálculo: el dinero que se paga es
+// negativo y el que se recibe positivo, y el tipo indica si los pagos se hacen al final (0, por
+// defecto) o al principio (1, o cualquier número distinto de cero) de cada periodo. Aceptan
+// cualquier número y calculan con f64, así que un importe exacto se obtiene con round o
+// decimal: round(pmt(8% / 12, 10, 10000), 2).
+pub(crate) const BUILTINS: &[Builtin] = &[
+    Builtin { name: "pmt", params: &[Number, Number, Number, Number, Number], required: 3, returns: Float, call: pmt },
+    Builtin { name: "fv", params: &[Number, Number, Number, Number, Number], required: 3, returns: Float, call: fv },
+    Builtin { name: "pv", params: &[Number, Number, Number, Number, Number], required: 3, returns: Float, call: pv },
+    Builtin { name: "npv", params: &[Number, List], required: 2, returns: Float, call: npv },
+    Builtin { name: "irr", params: &[List, Number], required: 1, returns: Float, call: irr },
+    Builtin { name: "rate", params: &[Number, Number, Number, Number, Number, Number], required: 3, returns: Float, call: rate },
+];
+
+// Estimación inicial de irr y rate si no se indica otra (la misma que en las hojas de cálculo)
+const DEFAULT_GUESS: f64 = 0.1;
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+// pmt(tasa, periodos, valor actual, valor futuro?, tipo?): pago periódico de un préstamo
+fn pmt(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (rate, nper, pv, fv) = (number(&args, 0), number(&args, 1), number(&args, 2), number(&args, 3));
+    let when = payment_type(&args);
+    let payment = match rate == 0.0 {
+        true => -(pv + fv) / nper,
+        false => {
+            let growth = (1.0 + rate).powf(nper);
+            -(pv * growth + fv) * rate / ((1.0 + rate * when) * (growth - 1.0))
+        }
+    };
+    finite(payment, || describe("pmt", &args))
+}
+
+// fv(tasa, periodos, pago, valor actual?, tipo?): valor final de una inversión
+fn fv(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (rate, nper, payment, pv) = (number(&args, 0), number(&args, 1), number(&args, 2), number(&args, 3));
+    let when = payment_type(&args);
+    finite(-balance(rate, nper, payment, pv, 0.0, when), || describe("fv", &args))
+}
+
+// pv(tasa, periodos, pago, valor futuro?, tipo?): valor actual de una serie de pagos
+fn pv(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (rate, nper, payment, fv) = (number(&args, 0), number(&args, 1), number(&args, 2), number(&args, 3));
+    let when = payment_type(&args);
+    let value = match rate == 0.0 {
+        true => -(fv + payment * nper),
+        false => {
+            let growth = (1.0 + rate).powf(nper);
+            -(fv + payment * (1.0 + rate * when) * (growth - 1.0) / rate) / growth
+        }
+    };
+    finite(value, || describe("pv", &args))
+}
+
+// npv(tasa, flujos): valor actual neto; como en las hojas de cálculo, el primer flujo ya se
+// descuenta un periodo, así que una inversión inicial se suma aparte: npv(10%, flujos) - 10000
+fn npv(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let rate = number(&args, 0);
+    let flows = cash_flows("npv", &args[1])?;
+    finite(net_present_value(rate, &flows) / (1.0 + rate), || describe("npv", &args))
+}
+
+// irr(flujos, estimación?): tasa que anula el valor actual neto de unos flujos periódicos
+fn irr(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let flows = cash_flows("irr", &args[0])?;
+    if !flows.iter().any(|flow| *flow > 0.0) || !flows.iter().any(|flow| *flow < 0.0) {
+        fail!("irr necesita al menos un flujo positivo y uno negativo");
+    }
+    let guess = args.get(1).map_or(DEFAULT_GUESS, |_| number(&args, 1));
+    solve("irr", guess, |rate| net_present_value(rate, &flows))
+}
+
+// rate(periodos, pago, valor actual, valor futuro?, tipo?, estimación?): tasa de interés por periodo
+fn rate(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (nper, payment, pv, fv) = (number(&args, 0), number(&args, 1), number(&args, 2), number(&args, 3));
+    let when = payment_type(&args);
+    let guess = args.get(5).map_or(DEFAULT_GUESS, |_| number(&args, 5));
+    solve("rate", guess, |rate| balance(rate, nper, payment, pv, fv, when))
+}
+
+// Ecuación de las anualidades, que vale cero cuando los cinco valores son coherentes:
+// pv * (1 + r)^n + pago * (1 + r * tipo) * ((1 + r)^n - 1) / r + fv
+fn balance(rate: f64, nper: f64, payment: f64, pv: f64, fv: f64, when: f64) -> f64 {
+    if rate == 0.0 {
+        return pv + payment * nper + fv;
+    }
+    let growth = (1.0 + rate).powf(nper);
+    pv * growth + payment * (1.0 + rate * when) * (growth - 1.0) / rate + fv
+}
+
+// Flujos descontados desde el periodo 0
+fn net_present_value(rate: f64, flows: &[f64]) -> f64 {
+    flows.iter().enumerate().map(|(period, flow)| flow / (1.0 + rate).powi(period as i32)).sum()
+}
+
+// Método de Newton con la derivada aproximada numéricamente. Igual que en las hojas de cálculo,
+// falla si no converge desde la estimación inicial.
+fn solve(name: &str, guess: f64, function: impl Fn(f64) -> f64) -> Result<EvalResult, ExprError> {
+    let mut rate = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let step = 1e-6 * rate.abs().max(1e-3);
+        let slope = (function(rate + step) - function(rate - step)) / (2.0 * step);
+        let next = rate - function(rate) / slope;
+        // Una tasa de -100% o menos no tiene sentido: (1 + r)^n se anula o cambia de signo
+        if !next.is_finite() || next <= -1.0 {
+            break;
+        }
+        if (next - rate).abs() < TOLERANCE * next.abs().max(1.0) {
+            return Ok(EvalResult::Float(next));
+        }
+        rate = next;
+    }
+    fail!("{} no converge; prueba con otra estimación inicial", name)
+}
+
+// Los argumentos opcionales que faltan valen 0
+fn number(args: &[EvalResult], index: usize) -> f64 {
+    args.get(index).and_then(EvalResult::as_float).unwrap_or(0.0)
+}
+
+// Quinto argumento: 0 si se paga al final de cada periodo y, como en las hojas de cálculo,
+// cualquier otro número (1, 1.0, 2...) si se paga al principio
+fn payment_type(args: &[EvalResult]) -> f64 {
+    match args.get(4) {
+        Some(when) if !when.is_zero() => 1.0,
+        _ => 0.0,
+    }
+}
+
+// Flujos de caja de una lista de números; los null se ignoran
+fn cash_flows(name: &str, list: &EvalResult) -> Result<Vec<f64>, ExprError> {
+    let mut flows = Vec::new();
+    for item in list_arg(list) {
+        match item.as_float() {
+            Some(flow) => flows.push(flow),
+            None if *item == EvalResult::Null => {}
+            None => fail!("{} espera una lista de números y recibió {}", name, item),
+        }
+    }
+    Ok(flows)
+}
+
+fn describe(name: &str, args: &[EvalResult]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::error::ExprError;
+    use crate::modules::functions::call_builtin;
+    use crate::modules::interprete::{Context, EvalResult};
+
+    // Los ejemplos de la documentación de las hojas de cálculo muestran dos decimales
+    fn call(name: &str, args: &[f64]) -> f64 {
+        let args = args.iter().map(|arg| EvalResult::Float(*arg)).collect();
+        match call_builtin(name, args, &mut Context::new()) {
+            Some(Ok(EvalResult::Float(value))) => value,
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+
+    fn call_with_type(name: &str, args: &[f64], when: i64) -> f64 {
+        let mut args: Vec<EvalResult> = args.iter().map(|arg| EvalResult::Float(*arg)).collect();
+        args.push(EvalResult::Int(when));
+        match call_builtin(name, args, &mut Context::new()) {
+            Some(Ok(EvalResult::Float(value))) => value,
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+
+    fn flows(values: &[f64]) -> EvalResult {
+        EvalResult::List(values.iter().map(|v| EvalResult::Float(*v)).collect())
+    }
+
+    fn assert_close(value: f64, expected: f64, precision: f64) {
+        assert!((value - expected).abs() < precision, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn test_pmt() {
+        assert_close(call("pmt", &[0.08 / 12.0, 10.0, 10000.0]), -1037.03, 0.005);
+        assert_close(call_with_type("pmt", &[0.08 / 12.0, 10.0, 10000.0, 0.0], 1), -1030.16, 0.005);
+        // Ahorro mensual para reunir 50000 en 18 años al 6%
+        assert_close(call("pmt", &[0.06 / 12.0, 18.0 * 12.0, 0.0, 50000.0]), -129.08, 0.005);
+        assert_close(call("pmt", &[0.0, 10.0, 1000.0]), -100.0, 1e-9);
+    }
+
+    #[test]
+    fn test_fv_and_pv() {
+        assert_close(call_with_type("fv", &[0.06 / 12.0, 10.0, -200.0, -500.0], 1), 2581.40, 0.005);
+        assert_close(call("fv", &[0.12 / 12.0, 12.0, -1000.0]), 12682.50, 0.005);
+        assert_close(call_with_type("fv", &[0.11 / 12.0, 35.0, -2000.0, 0.0], 1), 82846.25, 0.005);
+        assert_close(call("pv", &[0.08 / 12.0, 12.0 * 20.0, 500.0]), -59777.15, 0.005);
+    }
+
+    #[test]
+    fn test_npv_and_irr() {
+        let npv = |rate: f64, values: &[f64]| match call_builtin("npv", vec![EvalResult::Float(rate), flows(values)], &mut Context::new()) {
+            Some(Ok(EvalResult::Float(value))) => value,
+            other => panic!("npv: {:?}", other),
+        };
+        assert_close(npv(0.1, &[-10000.0, 3000.0, 4200.0, 6800.0]), 1188.44, 0.005);
+        assert_close(npv(0.08, &[8000.0, 9200.0, 10000.0, 12000.0, 14500.0]) - 40000.0, 1922.06, 0.005);
+
+        let irr = |values: &[f64], guess: Option<f64>| {
+            let mut args = vec![flows(values)];
+            args.extend(guess.map(EvalResult::Float));
+            match call_builtin("irr", args, &mut Context::new()) {
+                Some(Ok(EvalResult::Float(value))) => value,
+                other => panic!("irr: {:?}", other),
+            }
+        };
+        assert_close(irr(&[-70000.0, 12000.0, 15000.0, 18000.0, 21000.0], None), -0.021, 0.0005);
+        assert_close(irr(&[-70000.0, 12000.0, 15000.0, 18000.0, 21000.0, 26000.0], None), 0.087, 0.0005);
+        assert_close(irr(&[-70000.0, 12000.0, 15000.0], Some(-0.1)), -0.444, 0.0005);
+    }
+
+    #[test]
+    fn test_rate() {
+        let monthly = call("rate", &[4.0 * 12.0, -200.0, 8000.0]);
+        assert_close(monthly, 0.0077, 0.00005);
+        assert_close(monthly * 12.0, 0.0924, 0.00005);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert_eq!(
+            call_builtin("irr", vec![flows(&[100.0, 200.0])], &mut Context::new()),
+            Some(Err(ExprError::Runtime("irr necesita al menos un flujo positivo y uno negativo".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_payment_type_is_any_number() {
+        // Igual que en las hojas de cálculo, cualquier tipo distinto de cero es pago al principio
+        let when = |value: EvalResult| {
+            let mut args: Vec<EvalResult> = [0.08 / 12.0, 10.0, 10000.0, 0.0].iter().map(|arg| EvalResult::Float(*arg)).collect();
+            args.push(value);
+            match call_builtin("pmt", args, &mut Context::new()) {
+                Some(Ok(EvalResult::Float(value))) => value,
+                other => panic!("pmt: {:?}", other),
+            }
+        };
+        assert_close(when(EvalResult::Float(1.0)), -1030.16, 0.005);
+        assert_close(when(EvalResult::Int(2)), -1030.16, 0.005);
+        assert_close(when(EvalResult::Float(0.0)), -1037.03, 0.005);
+        assert_close(when(EvalResult::Decimal(rust_decimal::Decimal::ZERO)), -1037.03, 0.005);
+    }
+}
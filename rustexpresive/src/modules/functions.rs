@@ -0,0 +1,583 @@
+#[cfg(feature = "bignum")]
+use crate::modules::bignum;
+use crate::modules::collation::{caseless_eq, normalize};
+use crate::modules::decimal::{round, round_float, Rounding};
+use crate::modules::error::{fail, ExprError};
+use crate::modules::finance;
+use crate::modules::interprete::{call_closure, compare_numbers, finite, Closure, Context, EvalResult};
+use crate::modules::matching::CompiledRegex;
+use crate::modules::statistics;
+#[cfg(feature = "math")]
+use crate::modules::math;
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Tipos de los valores que aceptan y devuelven las funciones integradas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Any,
+    Bool,
+    Int,
+    Float,
+    // Decimal exacto (19.99d)
+    Decimal,
+    // Entero o decimal de cualquier tipo
+    Number,
+    String,
+    List,
+    Lambda,
+}
+
+impl ValueType {
+    // null se acepta en cualquier parámetro y hace que la función devuelva null
+    fn accepts(self, value: &EvalResult) -> bool {
+        if self == ValueType::Number {
+            return value.is_number() || *value == EvalResult::Null;
+        }
+        matches!(
+            (self, value),
+            (ValueType::Any, _)
+                | (_, EvalResult::Null)
+                | (ValueType::Bool, EvalResult::Bool(_))
+                | (ValueType::Int, EvalResult::Int(_))
+                | (ValueType::Float, EvalResult::Float(_) | EvalResult::Int(_))
+                | (ValueType::Decimal, EvalResult::Decimal(_) | EvalResult::Int(_))
+                | (ValueType::String, EvalResult::String(_))
+                | (ValueType::List, EvalResult::List(_))
+                | (ValueType::Lambda, EvalResult::Closure(_))
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValueType::Any => "any",
+            ValueType::Bool => "bool",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Decimal => "decimal",
+            ValueType::Number => "number",
+            ValueType::String => "string",
+            ValueType::List => "list",
+            ValueType::Lambda => "lambda",
+        }
+    }
+
+    // Nombre para los mensajes de error
+    fn description(self) -> &'static str {
+        match self {
+            ValueType::Any => "un valor",
+            ValueType::Bool => "un booleano",
+            ValueType::Int => "un entero",
+            ValueType::Float => "un decimal",
+            ValueType::Decimal => "un decimal exacto",
+            ValueType::Number => "un número",
+            ValueType::String => "un texto",
+            ValueType::List => "una lista",
+            ValueType::Lambda => "una lambda",
+        }
+    }
+}
+
+// Función integrada: su firma y la implementación, que recibe los argumentos ya comprobados
+pub(crate) struct Builtin {
+    pub(crate) name: &'static str,
+    pub(crate) params: &'static [ValueType],
+    // Los parámetros a partir de este número son opcionales
+    pub(crate) required: usize,
+    pub(crate) returns: ValueType,
+    pub(crate) call: fn(Vec<EvalResult>, &mut Context) -> Result<EvalResult, ExprError>,
+}
+
+impl Builtin {
+    // "una lista y una lambda", "un texto, un número y opcionalmente un número"
+    fn describe_params(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| match i >= self.required {
+                true => format!("opcionalmente {}", param.description()),
+                false => param.description().to_string(),
+            })
+            .collect();
+        match params.split_last() {
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} y {}", rest.join(", "), last),
+            None => "ningún argumento".to_string(),
+        }
+    }
+}
+
+use ValueType::{Any, Int, Lambda, List, Number, String as Text};
+
+// Registro de funciones integradas
+const BUILTINS: &[Builtin] = &[
+    // Funciones de orden superior sobre listas
+    Builtin { name: "any", params: &[List, Lambda], required: 2, returns: ValueType::Bool, call: any },
+    Builtin { name: "all", params: &[List, Lambda], required: 2, returns: ValueType::Bool, call: all },
+    Builtin { name: "filter", params: &[List, Lambda], required: 2, returns: List, call: filter },
+    Builtin { name: "map", params: &[List, Lambda], required: 2, returns: List, call: map },
+    Builtin { name: "count", params: &[List, Lambda], required: 1, returns: Int, call: count },
+    Builtin { name: "sort_by", params: &[List, Lambda], required: 2, returns: List, call: sort_by },
+    // Textos; las posiciones y longitudes cuentan caracteres visibles (grafemas), no bytes
+    Builtin { name: "contains", params: &[Text, Text], required: 2, returns: ValueType::Bool, call: contains },
+    Builtin { name: "starts_with", params: &[Text, Text], required: 2, returns: ValueType::Bool, call: starts_with },
+    Builtin { name: "ends_with", params: &[Text, Text], required: 2, returns: ValueType::Bool, call: ends_with },
+    Builtin { name: "len", params: &[Text], required: 1, returns: Int, call: len },
+    Builtin { name: "upper", params: &[Text], required: 1, returns: Text, call: upper },
+    Builtin { name: "lower", params: &[Text], required: 1, returns: Text, call: lower },
+    Builtin { name: "trim", params: &[Text], required: 1, returns: Text, call: trim },
+    Builtin { name: "substr", params: &[Text, Int, Int], required: 2, returns: Text, call: substr },
+    Builtin { name: "split", params: &[Text, Text], required: 2, returns: List, call: split },
+    Builtin { name: "replace", params: &[Text, Text, Text], required: 3, returns: Text, call: replace },
+    Builtin { name: "index_of", params: &[Text, Text], required: 2, returns: Int, call: index_of },
+    Builtin { name: "eq_ci", params: &[Text, Text], required: 2, returns: ValueType::Bool, call: eq_ci },
+    Builtin { name: "normalize", params: &[Text, Text], required: 1, returns: Text, call: normalize_text },
+    // Expresiones regulares con un patrón que no es literal (los literales se precompilan al analizar)
+    Builtin { name: "matches", params: &[Text, Text], required: 2, returns: ValueType::Bool, call: matches },
+    Builtin { name: "captures", params: &[Text, Text], required: 2, returns: List, call: captures },
+    // Números (round está siempre disponible porque lo necesitan los decimales exactos; el resto de
+    // funciones matemáticas están en el módulo math)
+    Builtin { name: "decimal", params: &[Any], required: 1, returns: ValueType::Decimal, call: to_decimal },
+    Builtin { name: "float", params: &[Number], required: 1, returns: ValueType::Float, call: to_float },
+    #[cfg(feature = "bignum")]
+    Builtin { name: "fraction", params: &[Number], required: 1, returns: Text, call: to_fraction },
+    Builtin { name: "round", params: &[Number, Int, Text], required: 1, returns: Number, call: round_number },
+];
+
+// Busca una función integrada; las matemáticas solo existen con la característica "math"
+fn find_builtin(name: &str) -> Option<&'static Builtin> {
+    #[cfg(feature = "math")]
+    let registries = [BUILTINS, statistics::BUILTINS, finance::BUILTINS, math::BUILTINS];
+    #[cfg(not(feature = "math"))]
+    let registries = [BUILTINS, statistics::BUILTINS, finance::BUILTINS];
+    registries.into_iter().flatten().find(|builtin| builtin.name == name)
+}
+
+// Constantes con nombre (PI, E) que se usan si no hay una variable que las oculte
+#[cfg(feature = "math")]
+pub(crate) fn constant(name: &str) -> Option<EvalResult> {
+    math::constant(name)
+}
+
+#[cfg(not(feature = "math"))]
+pub(crate) fn constant(_: &str) -> Option<EvalResult> {
+    None
+}
+
+// Funciones integradas del lenguaje. Devuelve None si no existe ninguna con ese nombre.
+pub fn call_builtin(name: &str, args: Vec<EvalResult>, context: &mut Context) -> Option<Result<EvalResult, ExprError>> {
+    let builtin = find_builtin(name)?;
+    Some(call(builtin, args, context))
+}
+
+fn call(builtin: &Builtin, args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let arity = builtin.required..=builtin.params.len();
+    if !arity.contains(&args.len()) || args.iter().zip(builtin.params).any(|(arg, param)| !param.accepts(arg)) {
+        fail!("{} espera {}", builtin.name, builtin.describe_params());
+    }
+    // Igual que los operadores, un argumento null da un resultado null
+    if args.iter().zip(builtin.params).any(|(arg, param)| *param != Any && *arg == EvalResult::Null) {
+        return Ok(EvalResult::Null);
+    }
+    (builtin.call)(args, context)
+}
+
+// Firma de una función integrada, por ejemplo "substr(string, int, int?) -> string"
+pub fn signature(name: &str) -> Option<String> {
+    let builtin = find_builtin(name)?;
+    let params: Vec<String> = builtin
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| match i >= builtin.required {
+            true => format!("{}?", param.name()),
+            false => param.name().to_string(),
+        })
+        .collect();
+    Some(format!("{}({}) -> {}", name, params.join(", "), builtin.returns.name()))
+}
+
+fn any(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (items, closure) = list_and_lambda(args);
+    for item in items {
+        if predicate("any", &closure, item, context)? {
+            return Ok(EvalResult::Bool(true));
+        }
+    }
+    Ok(EvalResult::Bool(false))
+}
+
+fn all(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (items, closure) = list_and_lambda(args);
+    for item in items {
+        if !predicate("all", &closure, item, context)? {
+            return Ok(EvalResult::Bool(false));
+        }
+    }
+    Ok(EvalResult::Bool(true))
+}
+
+fn filter(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (items, closure) = list_and_lambda(args);
+    let mut kept = Vec::new();
+    for item in items {
+        if predicate("filter", &closure, item.clone(), context)? {
+            kept.push(item);
+        }
+    }
+    Ok(EvalResult::List(kept))
+}
+
+fn map(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (items, closure) = list_and_lambda(args);
+    let mapped = items
+        .into_iter()
+        .map(|item| call_closure(&closure, vec![item], context))
+        .collect::<Result<_, _>>()?;
+    Ok(EvalResult::List(mapped))
+}
+
+fn count(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    if args.len() == 1 {
+        return Ok(EvalResult::Int(list_arg(&args[0]).len() as i64));
+    }
+    let (items, closure) = list_and_lambda(args);
+    let mut matching = 0;
+    for item in items {
+        if predicate("count", &closure, item, context)? {
+            matching += 1;
+        }
+    }
+    Ok(EvalResult::Int(matching))
+}
+
+fn sort_by(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (items, closure) = list_and_lambda(args);
+    let mut keyed: Vec<(EvalResult, EvalResult)> = items
+        .into_iter()
+        .map(|item| Ok((call_closure(&closure, vec![item.clone()], context)?, item)))
+        .collect::<Result<_, ExprError>>()?;
+    let texts = keyed.iter().all(|(key, _)| matches!(key, EvalResult::String(_)));
+    let numbers = keyed.iter().all(|(key, _)| key.as_float().is_some());
+    if !texts && !numbers {
+        fail!("sort_by solo puede ordenar por claves numéricas o de texto del mismo tipo");
+    }
+    // Ordenación estable: los elementos con la misma clave mantienen su orden
+    let text_order = context.text_order()?;
+    keyed.sort_by(|(a, _), (b, _)| compare_keys(a, b, &text_order));
+    Ok(EvalResult::List(keyed.into_iter().map(|(_, item)| item).collect()))
+}
+
+fn contains(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::Bool(text_arg(&args[0]).contains(text_arg(&args[1]))))
+}
+
+fn starts_with(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::Bool(text_arg(&args[0]).starts_with(text_arg(&args[1]))))
+}
+
+fn ends_with(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::Bool(text_arg(&args[0]).ends_with(text_arg(&args[1]))))
+}
+
+fn len(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::Int(text_arg(&args[0]).graphemes(true).count() as i64))
+}
+
+fn upper(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::String(text_arg(&args[0]).to_uppercase()))
+}
+
+fn lower(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::String(text_arg(&args[0]).to_lowercase()))
+}
+
+fn trim(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::String(text_arg(&args[0]).trim().to_string()))
+}
+
+// substr(texto, inicio, longitud?): si la longitud falta se toma hasta el final
+fn substr(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let start = position_arg("substr", &args[1])?;
+    let graphemes = text_arg(&args[0]).graphemes(true).skip(start);
+    let text = match args.get(2) {
+        Some(length) => graphemes.take(position_arg("substr", length)?).collect(),
+        None => graphemes.collect(),
+    };
+    Ok(EvalResult::String(text))
+}
+
+// split con separador vacío separa cada carácter
+fn split(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (text, separator) = (text_arg(&args[0]), text_arg(&args[1]));
+    let parts: Vec<&str> = match separator.is_empty() {
+        true => text.graphemes(true).collect(),
+        false => text.split(separator).collect(),
+    };
+    Ok(EvalResult::List(parts.into_iter().map(|part| EvalResult::String(part.to_string())).collect()))
+}
+
+fn replace(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::String(text_arg(&args[0]).replace(text_arg(&args[1]), text_arg(&args[2]))))
+}
+
+// Posición (en caracteres) de la primera aparición, o -1 si no aparece
+fn index_of(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let text = text_arg(&args[0]);
+    match text.find(text_arg(&args[1])) {
+        Some(byte) => Ok(EvalResult::Int(text[..byte].graphemes(true).count() as i64)),
+        None => Ok(EvalResult::Int(-1)),
+    }
+}
+
+// Igual que el operador ~=
+fn eq_ci(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    Ok(EvalResult::Bool(caseless_eq(text_arg(&args[0]), text_arg(&args[1]))))
+}
+
+// normalize(texto, forma?): NFC por defecto
+fn normalize_text(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let form = args.get(1).map(text_arg).unwrap_or("NFC");
+    match normalize(text_arg(&args[0]), form) {
+        Some(text) => Ok(EvalResult::String(text)),
+        None => fail!("Forma de normalización desconocida: {} (se esperaba NFC, NFD, NFKC o NFKD)", form),
+    }
+}
+
+fn matches(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let regex = CompiledRegex::try_new(text_arg(&args[1])).map_err(ExprError::Runtime)?;
+    Ok(EvalResult::Bool(regex.is_match(text_arg(&args[0]))))
+}
+
+fn captures(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let regex = CompiledRegex::try_new(text_arg(&args[1])).map_err(ExprError::Runtime)?;
+    Ok(capture_list(&regex, text_arg(&args[0])))
+}
+
+// decimal(x): convierte un número o un texto como "19.99" en un decimal exacto
+fn to_decimal(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let decimal = match &args[0] {
+        EvalResult::Null => return Ok(EvalResult::Null),
+        EvalResult::Decimal(n) => Some(*n),
+        EvalResult::Int(n) => Some(Decimal::from(*n)),
+        EvalResult::Float(n) => Decimal::try_from(*n).ok(),
+        EvalResult::String(text) => text.trim().parse().ok(),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(n) => bignum::to_decimal(n),
+        other => fail!("decimal espera un número o un texto y recibió {:?}", other),
+    };
+    match decimal {
+        Some(decimal) => Ok(EvalResult::Decimal(decimal)),
+        None => fail!("No se puede convertir {} en un decimal exacto", args[0]),
+    }
+}
+
+// float(x): conversión explícita a f64, la única forma de que un número exacto deje de serlo
+fn to_float(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    match args[0].as_float() {
+        Some(n) => finite(n, || format!("float({})", args[0])),
+        None => fail!("No se puede convertir {} en un decimal", args[0]),
+    }
+}
+
+// fraction(x): forma exacta de un número como fracción ("5/6"), que al mostrarse se ve en decimal
+#[cfg(feature = "bignum")]
+fn to_fraction(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let fraction = match &args[0] {
+        EvalResult::Int(n) => bignum::from_int(*n),
+        EvalResult::Decimal(n) => bignum::from_decimal(*n),
+        EvalResult::Rational(n) => n.clone(),
+        other => fail!("fraction espera un número exacto y recibió {}", other),
+    };
+    Ok(EvalResult::String(fraction.to_string()))
+}
+
+// round(x, cifras?, modo?): por defecto sin decimales y con los empates alejándose del cero ("half_up").
+// Un decimal exacto conserva la escala pedida: round(2.5d, 2) es 2.50.
+fn round_number(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let scale = match args.get(1) {
+        None => 0,
+        Some(EvalResult::Int(n)) if (0..=28).contains(n) => *n as u32,
+        Some(other) => fail!("round espera entre 0 y 28 cifras decimales y recibió {}", other),
+    };
+    let rounding = match args.get(2).map(text_arg) {
+        None => Rounding::HalfUp,
+        Some(name) => match Rounding::from_name(name) {
+            Some(rounding) => rounding,
+            None => fail!("Modo de redondeo desconocido: {} (usa half_even, half_up, half_down, up, down, ceiling o floor)", name),
+        },
+    };
+    match args[0] {
+        EvalResult::Int(n) => Ok(EvalResult::Int(n)),
+        EvalResult::Float(n) => Ok(EvalResult::Float(round_float(n, scale, rounding))),
+        EvalResult::Decimal(n) => Ok(EvalResult::Decimal(round(n, scale, rounding))),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(ref n) => bignum::round_rational(n.clone(), scale, rounding),
+        _ => unreachable!("la firma garantiza un número"),
+    }
+}
+
+// Lista con la coincidencia completa y sus grupos (null si un grupo no participa); vacía si no coincide
+pub(crate) fn capture_list(regex: &CompiledRegex, text: &str) -> EvalResult {
+    let groups = regex.captures(text).unwrap_or_default();
+    let groups = groups
+        .into_iter()
+        .map(|group| group.map(EvalResult::String).unwrap_or(EvalResult::Null))
+        .collect();
+    EvalResult::List(groups)
+}
+
+// Los argumentos ya vienen comprobados por la firma, así que aquí solo se desempaquetan
+fn text_arg(value: &EvalResult) -> &str {
+    match value {
+        EvalResult::String(text) => text,
+        _ => unreachable!("la firma garantiza un texto"),
+    }
+}
+
+pub(crate) fn list_arg(value: &EvalResult) -> &[EvalResult] {
+    match value {
+        EvalResult::List(items) => items,
+        _ => unreachable!("la firma garantiza una lista"),
+    }
+}
+
+// Posiciones y longitudes: enteros no negativos
+fn position_arg(name: &str, value: &EvalResult) -> Result<usize, ExprError> {
+    match value {
+        EvalResult::Int(n) if *n >= 0 => Ok(*n as usize),
+        _ => fail!("{} espera posiciones enteras no negativas y recibió {:?}", name, value),
+    }
+}
+
+// Separa los argumentos (lista, lambda) de las funciones de orden superior
+fn list_and_lambda(args: Vec<EvalResult>) -> (Vec<EvalResult>, Rc<Closure>) {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(EvalResult::List(items)), Some(EvalResult::Closure(closure))) => (items, closure),
+        _ => unreachable!("la firma garantiza una lista y una lambda"),
+    }
+}
+
+// Una lambda que devuelve null no se cumple, igual que un WHERE de SQL
+fn predicate(name: &str, closure: &Closure, item: EvalResult, context: &mut Context) -> Result<bool, ExprError> {
+    match call_closure(closure, vec![item], context)? {
+        EvalResult::Bool(value) => Ok(value),
+        EvalResult::Null => Ok(false),
+        _ => fail!("La lambda de {} debe devolver un booleano", name),
+    }
+}
+
+// Claves de ordenación: números con números y cadenas con cadenas (en el orden del idioma)
+fn compare_keys(a: &EvalResult, b: &EvalResult, text_order: impl Fn(&str, &str) -> Ordering) -> Ordering {
+    match (a, b) {
+        (EvalResult::String(l), EvalResult::String(r)) => text_order(l, r),
+        _ => compare_numbers(a, b).unwrap_or(Ordering::Equal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_builtin() {
+        assert_eq!(call_builtin("no_existe", vec![], &mut Context::new()), None);
+    }
+
+    #[test]
+    fn test_count_without_lambda() {
+        let items = EvalResult::List(vec![EvalResult::Bool(true), EvalResult::String("a".to_string())]);
+        assert_eq!(call_builtin("count", vec![items], &mut Context::new()), Some(Ok(EvalResult::Int(2))));
+    }
+
+    #[test]
+    fn test_higher_order_without_lambda() {
+        let items = EvalResult::List(vec![]);
+        assert_eq!(
+            call_builtin("filter", vec![items, EvalResult::Int(1)], &mut Context::new()),
+            Some(Err(ExprError::Runtime("filter espera una lista y una lambda".to_string())))
+        );
+    }
+
+    fn text(value: &str) -> EvalResult {
+        EvalResult::String(value.to_string())
+    }
+
+    fn call(name: &str, args: Vec<EvalResult>) -> EvalResult {
+        call_builtin(name, args, &mut Context::new()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_signatures() {
+        assert_eq!(signature("substr").unwrap(), "substr(string, int, int?) -> string");
+        assert_eq!(signature("filter").unwrap(), "filter(list, lambda) -> list");
+        assert_eq!(signature("no_existe"), None);
+    }
+
+    #[test]
+    fn test_string_functions_are_unicode_aware() {
+        // "e" + acento combinado y un emoji con tono de piel cuentan como un solo carácter
+        let word = "cafe\u{301} 👍🏽!";
+        assert_eq!(call("len", vec![text(word)]), EvalResult::Int(7));
+        assert_eq!(call("substr", vec![text(word), EvalResult::Int(3), EvalResult::Int(3)]), text("e\u{301} 👍🏽"));
+        assert_eq!(call("index_of", vec![text(word), text("!")]), EvalResult::Int(6));
+        assert_eq!(call("index_of", vec![text(word), text("x")]), EvalResult::Int(-1));
+        assert_eq!(call("upper", vec![text("straße")]), text("STRASSE"));
+        assert_eq!(call("lower", vec![text("ÁRBOL")]), text("árbol"));
+        assert_eq!(call("trim", vec![text("\u{3000} hola\n")]), text("hola"));
+        assert_eq!(
+            call("split", vec![text("ñu"), text("")]),
+            EvalResult::List(vec![text("ñ"), text("u")])
+        );
+    }
+
+    #[test]
+    fn test_string_functions_propagate_null() {
+        assert_eq!(call("upper", vec![EvalResult::Null]), EvalResult::Null);
+        assert_eq!(call("contains", vec![text("abc"), EvalResult::Null]), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_normalize_unknown_form() {
+        assert_eq!(
+            call_builtin("normalize", vec![text("a"), text("bogus")], &mut Context::new()),
+            Some(Err(ExprError::Runtime(
+                "Forma de normalización desconocida: bogus (se esperaba NFC, NFD, NFKC o NFKD)".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(call("round", vec![EvalResult::Float(2.5)]), EvalResult::Float(3.0));
+        assert_eq!(call("round", vec![EvalResult::Float(2.675), EvalResult::Int(2)]), EvalResult::Float(2.68));
+        assert_eq!(call("round", vec![EvalResult::Float(-2.5), EvalResult::Int(0), text("floor")]), EvalResult::Float(-3.0));
+        assert_eq!(call("round", vec![EvalResult::Int(7), EvalResult::Int(2)]), EvalResult::Int(7));
+        assert_eq!(signature("round").unwrap(), "round(number, int?, string?) -> number");
+        assert_eq!(
+            call_builtin("round", vec![EvalResult::Float(1.0), EvalResult::Int(2), text("bankers")], &mut Context::new()),
+            Some(Err(ExprError::Runtime(
+                "Modo de redondeo desconocido: bankers (usa half_even, half_up, half_down, up, down, ceiling o floor)".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_float_conversion() {
+        assert_eq!(call("float", vec![EvalResult::Int(2)]), EvalResult::Float(2.0));
+        assert_eq!(call("float", vec![EvalResult::Decimal(Decimal::new(1999, 2))]), EvalResult::Float(19.99));
+        assert_eq!(call("float", vec![EvalResult::Null]), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_builtin_signature_mismatch() {
+        assert_eq!(
+            call_builtin("substr", vec![text("abc"), text("1")], &mut Context::new()),
+            Some(Err(ExprError::Runtime("substr espera un texto, un entero y opcionalmente un entero".to_string())))
+        );
+    }
+}
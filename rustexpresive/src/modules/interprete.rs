@@ -1,17 +1,61 @@
 
-use crate::modules::parser::Expr;
+#[cfg(feature = "bignum")]
+use crate::modules::bignum;
+use crate::modules::collation::caseless_eq;
+#[cfg(feature = "collation")]
+use crate::modules::collation::Collation;
+use crate::modules::decimal::{round, Rounding};
+use crate::modules::error::{fail, ExprError};
+use crate::modules::functions::{call_builtin, capture_list, constant};
+use crate::modules::loader::{default_namespace, ModuleLoader};
+use crate::modules::matching::{CompiledRegex, Wildcard};
+use crate::modules::parser::{Expr, Pattern};
+#[cfg(feature = "bignum")]
+use num_rational::BigRational;
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::rc::Rc;
 
+#[derive(Clone)]
 pub enum EvalResult {
+    Null,
     Bool(bool),
     String(String),
+    Int(i64),
+    Float(f64),
+    Decimal(Decimal),
+    // Entero que no cabe en un i64 o fracción exacta (característica bignum)
+    #[cfg(feature = "bignum")]
+    Rational(BigRational),
+    List(Vec<EvalResult>),
+    Map(BTreeMap<String, EvalResult>),
+    Closure(Rc<Closure>),
+}
+
+// Lambda junto con las variables locales visibles donde se creó
+pub struct Closure {
+    params: Vec<String>,
+    body: Expr,
+    captured: HashMap<String, EvalResult>,
+    namespace: Option<String>,
 }
 
 impl PartialEq for EvalResult {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (EvalResult::Null, EvalResult::Null) => true,
             (EvalResult::Bool(l), EvalResult::Bool(r)) => l == r,
             (EvalResult::String(l), EvalResult::String(r)) => l == r,
+            (EvalResult::Int(l), EvalResult::Int(r)) => l == r,
+            (EvalResult::Float(l), EvalResult::Float(r)) => l == r,
+            (EvalResult::Decimal(l), EvalResult::Decimal(r)) => l == r,
+            #[cfg(feature = "bignum")]
+            (EvalResult::Rational(l), EvalResult::Rational(r)) => l == r,
+            (EvalResult::List(l), EvalResult::List(r)) => l == r,
+            (EvalResult::Map(l), EvalResult::Map(r)) => l == r,
             _ => false,
         }
     }
@@ -20,163 +64,2209 @@ impl PartialEq for EvalResult {
 impl fmt::Debug for EvalResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            EvalResult::Null => write!(f, "Null"),
             EvalResult::Bool(ref b) => write!(f, "Bool({})", b),
             EvalResult::String(ref s) => write!(f, "Str({})", s),
+            EvalResult::Int(ref n) => write!(f, "Int({})", n),
+            EvalResult::Float(ref n) => write!(f, "Float({})", n),
+            EvalResult::Decimal(ref n) => write!(f, "Decimal({})", n),
+            #[cfg(feature = "bignum")]
+            EvalResult::Rational(ref n) => write!(f, "Rational({})", n),
+            EvalResult::List(ref items) => write!(f, "List({:?})", items),
+            EvalResult::Map(ref entries) => write!(f, "Map({:?})", entries),
+            EvalResult::Closure(ref closure) => write!(f, "Closure({})", closure.params.join(", ")),
         }
     }
 }
 
+// Texto de un valor tal y como se muestra al usuario y se inserta en las interpolaciones
+impl fmt::Display for EvalResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalResult::Null => write!(f, "null"),
+            EvalResult::Bool(b) => write!(f, "{}", b),
+            EvalResult::String(s) => write!(f, "{}", s),
+            EvalResult::Int(n) => write!(f, "{}", n),
+            EvalResult::Float(n) => write!(f, "{}", n),
+            EvalResult::Decimal(n) => write!(f, "{}", n),
+            #[cfg(feature = "bignum")]
+            EvalResult::Rational(n) => write!(f, "{}", bignum::format(n)),
+            EvalResult::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            EvalResult::Map(entries) => {
+                let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            EvalResult::Closure(_) => write!(f, "<lambda>"),
+        }
+    }
+}
 
-pub fn evaluate(expr: Expr) -> EvalResult {
-    match expr {
-        // Literal booleano
-        Expr::BooleanLiteral(value) => EvalResult::Bool(value),
+impl EvalResult {
+    // Valor numérico como decimal, para operar enteros con decimales
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            EvalResult::Int(n) => Some(*n as f64),
+            EvalResult::Float(n) => Some(*n),
+            EvalResult::Decimal(n) => f64::try_from(*n).ok(),
+            #[cfg(feature = "bignum")]
+            EvalResult::Rational(n) => bignum::to_float(n),
+            _ => None,
+        }
+    }
 
-        // String
-        Expr::StringLiteral(s) => EvalResult::String(s),
+    pub(crate) fn is_number(&self) -> bool {
+        self.as_float().is_some()
+    }
 
-        // Negación lógica
-        Expr::Not(boxed_expr) => {
-            let result = evaluate(*boxed_expr);
-            match result {
-                EvalResult::Bool(value) => EvalResult::Bool(!value),
-                EvalResult::String(_) => panic!("No se puede aplicar NOT a un String"),
-            }
+    // Si el valor es un cero, comprobado sobre el número exacto: una fracción tan pequeña que
+    // como f64 se queda en 0.0 no es un cero
+    pub(crate) fn is_zero(&self) -> bool {
+        match self {
+            EvalResult::Int(n) => *n == 0,
+            EvalResult::Float(n) => *n == 0.0,
+            EvalResult::Decimal(n) => n.is_zero(),
+            #[cfg(feature = "bignum")]
+            EvalResult::Rational(n) => bignum::is_zero(n),
+            _ => false,
         }
+    }
 
-        // Operación AND
-        Expr::And(boxed_left, boxed_right) => {
-            let left = evaluate(*boxed_left);
-            let right = evaluate(*boxed_right);
+    // Valor numérico como decimal exacto; los decimales binarios (f64) no se convierten solos
+    pub(crate) fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            EvalResult::Int(n) => Some(Decimal::from(*n)),
+            EvalResult::Decimal(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+// Profundidad máxima de llamadas a funciones por defecto (protege frente a recursión infinita)
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 64;
 
-            match (left, right) {
-                (EvalResult::Bool(l), EvalResult::Bool(r)) => EvalResult::Bool(l && r),
-                _ => panic!("Ambos operandos deben ser booleanos para AND"),
-            }
+// Función definida en el propio lenguaje con fn nombre(params) = cuerpo
+#[derive(Clone)]
+struct Function {
+    params: Vec<String>,
+    body: Expr,
+    // Espacio de nombres del módulo en el que se definió, si lo hay
+    namespace: Option<String>,
+}
+
+// Valores de las variables y funciones disponibles durante la evaluación
+pub struct Context {
+    variables: HashMap<String, EvalResult>,
+    // Ámbitos léxicos abiertos por los let, del más externo al más interno
+    scopes: Vec<HashMap<String, EvalResult>>,
+    // Asignaciones del programa en curso; las ve todo su código, también el cuerpo de sus funciones
+    program: HashMap<String, EvalResult>,
+    functions: HashMap<String, Function>,
+    call_depth: usize,
+    max_call_depth: usize,
+    modules: ModuleLoader,
+    // Espacio de nombres del módulo cuyo código se está evaluando
+    namespace: Option<String>,
+    // Orden de los textos en <, > y sort_by; se crea al usarlo por primera vez
+    #[cfg(feature = "collation")]
+    collation: Option<Collation>,
+    #[cfg(feature = "collation")]
+    locale: String,
+    // Escala y redondeo que se aplican al resultado de cada operación con decimales exactos
+    decimal_scale: Option<(u32, Rounding)>,
+    // Si avg, median, etc. de una lista vacía dan null en lugar de un error
+    null_on_empty_list: bool,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            variables: HashMap::new(),
+            scopes: Vec::new(),
+            program: HashMap::new(),
+            functions: HashMap::new(),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            modules: ModuleLoader::new(),
+            namespace: None,
+            #[cfg(feature = "collation")]
+            collation: None,
+            #[cfg(feature = "collation")]
+            locale: String::new(),
+            decimal_scale: None,
+            null_on_empty_list: false,
         }
+    }
+}
 
-        // Operación OR
-        Expr::Or(boxed_left, boxed_right) => {
-            let left = evaluate(*boxed_left);
-            let right = evaluate(*boxed_right);
+impl Context {
+    pub fn new() -> Self {
+        Context::default()
+    }
 
-            match (left, right) {
-                (EvalResult::Bool(l), EvalResult::Bool(r)) => EvalResult::Bool(l || r),
-                _ => panic!("Ambos operandos deben ser booleanos para OR"),
-            }
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    // Idioma con el que se ordenan los textos, por ejemplo "es" o "sv"; por defecto el orden raíz de
+    // Unicode. Si el idioma no es válido se devuelve el error y se mantiene el anterior.
+    #[cfg(feature = "collation")]
+    pub fn set_locale(&mut self, locale: &str) -> Result<(), ExprError> {
+        self.collation = Some(Collation::new(locale)?);
+        self.locale = locale.to_string();
+        Ok(())
+    }
+
+    // Por ejemplo (2, Rounding::HalfEven) para importes: 10d / 3 da 3.33 en lugar de 3.3333333333333333333333333333
+    pub fn set_decimal_scale(&mut self, scale: u32, rounding: Rounding) {
+        self.decimal_scale = Some((scale, rounding));
+    }
+
+    // Con true, las estadísticas de una lista vacía (avg([]), max_of([])) dan null; por defecto son un error
+    pub fn set_null_on_empty_list(&mut self, null: bool) {
+        self.null_on_empty_list = null;
+    }
+
+    pub(crate) fn null_on_empty_list(&self) -> bool {
+        self.null_on_empty_list
+    }
+
+    // Orden de los textos: el del idioma del contexto o, sin la característica collation, el de sus
+    // puntos de código
+    #[cfg(feature = "collation")]
+    pub(crate) fn text_order(&mut self) -> Result<impl Fn(&str, &str) -> Ordering + '_, ExprError> {
+        if self.collation.is_none() {
+            self.collation = Some(Collation::new(&self.locale)?);
         }
+        let collation = self.collation.as_ref().unwrap();
+        Ok(|left: &str, right: &str| collation.compare(left, right))
+    }
 
-        // Igualdad
-        Expr::Equal(boxed_left, boxed_right) => {
-            let left = evaluate(*boxed_left);
-            let right = evaluate(*boxed_right);
+    #[cfg(not(feature = "collation"))]
+    pub(crate) fn text_order(&mut self) -> Result<impl Fn(&str, &str) -> Ordering, ExprError> {
+        Ok(str::cmp)
+    }
 
-            match (left, right) {
-                (EvalResult::Bool(l), EvalResult::Bool(r)) => EvalResult::Bool(l == r),
-                (EvalResult::String(l), EvalResult::String(r)) => EvalResult::Bool(l == r),
-                _ => panic!("Solo puedes comparar booleanos o cadenas con igualdad"),
+    // Registra un módulo con nombre para poder usarlo con import "nombre"
+    pub fn register_module(&mut self, name: &str, source: &str) {
+        self.modules.register(name, source);
+    }
+
+    // Carpeta desde la que se cargan los módulos que no se han registrado; los import no pueden salir
+    // de ella y, si no se indica, solo se pueden importar módulos registrados
+    pub fn set_module_root(&mut self, root: impl Into<PathBuf>) {
+        self.modules.set_root(root);
+    }
+
+    pub fn set(&mut self, name: &str, value: EvalResult) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    // Busca primero en los ámbitos locales, de forma que los let ocultan a las variables externas, y
+    // después en las asignaciones del programa. Dentro del código de un módulo se encuentran en su
+    // lugar sus constantes sin cualificar.
+    pub fn get(&self, name: &str) -> Option<&EvalResult> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.qualified(name).and_then(|name| self.variables.get(&name)))
+            .or_else(|| self.program.get(name).filter(|_| self.namespace.is_none()))
+            .or_else(|| self.variables.get(name))
+    }
+
+    fn find_function(&self, name: &str) -> Option<&Function> {
+        self.qualified(name)
+            .and_then(|name| self.functions.get(&name))
+            .or_else(|| self.functions.get(name))
+    }
+
+    // Copia de todas las variables locales visibles, para que las lambdas las capturen
+    fn capture_scopes(&self) -> HashMap<String, EvalResult> {
+        let mut captured = match self.namespace {
+            None => self.program.clone(),
+            Some(_) => HashMap::new(),
+        };
+        for scope in &self.scopes {
+            captured.extend(scope.iter().map(|(name, value)| (name.clone(), value.clone())));
+        }
+        captured
+    }
+
+    fn qualified(&self, name: &str) -> Option<String> {
+        self.namespace.as_ref().map(|namespace| format!("{}::{}", namespace, name))
+    }
+
+    fn push_scope(&mut self, name: String, value: EvalResult) {
+        self.scopes.push(HashMap::from([(name, value)]));
+    }
+
+    // Las asignaciones de un programa se guardan aparte, nunca en las variables del contexto
+    fn assign(&mut self, name: String, value: EvalResult) {
+        self.program.insert(name, value);
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
+
+// Igualdad de dos valores en match e in: los números se comparan por su valor (1 == 1.0), también
+// dentro de listas y mapas, y aquí un null es igual a otro null
+fn same_value(left: &EvalResult, right: &EvalResult) -> bool {
+    match (left, right) {
+        _ if left.is_number() && right.is_number() => compare_numbers(left, right) == Some(Ordering::Equal),
+        (EvalResult::List(l), EvalResult::List(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same_value(l, r))
+        }
+        (EvalResult::Map(l), EvalResult::Map(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|((lk, lv), (rk, rv))| lk == rk && same_value(lv, rv))
+        }
+        _ => left == right,
+    }
+}
+
+// Orden de dos números; los enteros y los decimales exactos se comparan sin pasar por f64
+pub(crate) fn compare_numbers(left: &EvalResult, right: &EvalResult) -> Option<Ordering> {
+    #[cfg(feature = "bignum")]
+    if let Some((l, r)) = bignum::exact_operands(left, right, false) {
+        return Some(l.cmp(&r));
+    }
+    match (left, right) {
+        (EvalResult::Int(l), EvalResult::Int(r)) => Some(l.cmp(r)),
+        _ => match (left.as_decimal(), right.as_decimal()) {
+            (Some(l), Some(r)) => Some(l.cmp(&r)),
+            _ => left.as_float()?.partial_cmp(&right.as_float()?),
+        },
+    }
+}
+
+// Compara dos valores del mismo tipo (== y !=); None si alguno es null
+fn equals(left: EvalResult, right: EvalResult, op: &str) -> Result<Option<bool>, ExprError> {
+    let equal = match (left, right) {
+        (EvalResult::Null, _) | (_, EvalResult::Null) => return Ok(None),
+        (EvalResult::Bool(l), EvalResult::Bool(r)) => l == r,
+        (EvalResult::String(l), EvalResult::String(r)) => l == r,
+        (left, right) if left.is_number() && right.is_number() => same_value(&left, &right),
+        (EvalResult::List(l), EvalResult::List(r)) => match l.len() == r.len() {
+            true => return equal_items(l.into_iter().zip(r), op),
+            false => false,
+        },
+        (EvalResult::Map(l), EvalResult::Map(r)) => match l.keys().eq(r.keys()) {
+            true => return equal_items(l.into_values().zip(r.into_values()), op),
+            false => false,
+        },
+        _ => fail!("Solo puedes comparar booleanos, cadenas, números, listas o mapas con {}", op),
+    };
+    Ok(Some(equal))
+}
+
+// Listas y mapas se comparan elemento a elemento con las mismas reglas que los valores sueltos
+// ([1] == [1.0]). Basta un par distinto para que sean distintos; si no lo hay, un null en algún
+// par hace que el resultado sea null, igual que null == null ([null] == [null] es null).
+fn equal_items(pairs: impl Iterator<Item = (EvalResult, EvalResult)>, op: &str) -> Result<Option<bool>, ExprError> {
+    let mut equal = Some(true);
+    for (left, right) in pairs {
+        match equals(left, right, op)? {
+            Some(false) => return Ok(Some(false)),
+            None => equal = None,
+            Some(true) => {}
+        }
+    }
+    Ok(equal)
+}
+
+// Operando de AND / OR: Some(valor) para booleanos y None para null
+fn logic_operand(value: EvalResult, op: &str) -> Result<Option<bool>, ExprError> {
+    match value {
+        EvalResult::Bool(b) => Ok(Some(b)),
+        EvalResult::Null => Ok(None),
+        _ => fail!("Ambos operandos deben ser booleanos para {}", op),
+    }
+}
+
+// Elemento de una lista por posición o valor de un mapa por clave
+fn index_value(target: EvalResult, index: EvalResult, path: Option<String>) -> Result<EvalResult, ExprError> {
+    match (target, index) {
+        (EvalResult::Map(entries), EvalResult::String(key)) => get_key(entries, &key, path),
+        (EvalResult::List(items), EvalResult::Int(n)) => {
+            if n < 0 {
+                fail!("El índice de una lista debe ser un entero no negativo: {}", n);
             }
+            let len = items.len();
+            match items.into_iter().nth(n as usize) {
+                Some(item) => Ok(item),
+                None => fail!("Índice fuera de rango: {} (longitud {})", n, len),
+            }
+        }
+        (EvalResult::List(_), EvalResult::Float(n)) => {
+            fail!("El índice de una lista debe ser un entero no negativo: {}", n)
         }
+        _ => fail!("Solo se puede indexar una lista con un número o un mapa con una cadena"),
+    }
+}
 
-        // Desigualdad
-        Expr::NotEqual(boxed_left, boxed_right) => {
-            let left = evaluate(*boxed_left);
-            let right = evaluate(*boxed_right);
+fn evaluate_case(branches: Vec<(Expr, Expr)>, default: Option<Box<Expr>>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    for (condition, result) in branches {
+        match try_evaluate_with(condition, context)? {
+            EvalResult::Bool(true) => return try_evaluate_with(result, context),
+            EvalResult::Bool(false) | EvalResult::Null => {}
+            _ => fail!("La condición de un when debe ser booleana"),
+        }
+    }
+    match default {
+        Some(boxed_default) => try_evaluate_with(*boxed_default, context),
+        None => fail!("Ninguna rama del case coincide y no hay else"),
+    }
+}
 
-            match (left, right) {
-                (EvalResult::Bool(l), EvalResult::Bool(r)) => EvalResult::Bool(l != r),
-                (EvalResult::String(l), EvalResult::String(r)) => EvalResult::Bool(l != r),
-                _ => panic!("Solo puedes comparar booleanos o cadenas con desigualdad"),
+fn evaluate_match(subject: Expr, arms: Vec<(Vec<Pattern>, Expr)>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let subject = try_evaluate_with(subject, context)?;
+    for (patterns, result) in arms {
+        for pattern in patterns {
+            let matches = match pattern {
+                Pattern::Wildcard => true,
+                Pattern::Literal(literal) => same_value(&try_evaluate_with(literal, context)?, &subject),
+            };
+            if matches {
+                return try_evaluate_with(result, context);
             }
         }
     }
+    fail!("Ningún brazo del match coincide con el valor {:?}", subject)
 }
 
+// Comprueba si un valor está en una lista, o si una cadena es clave de un mapa (in / not in)
+fn contains(list: EvalResult, item: &EvalResult) -> Result<bool, ExprError> {
+    match (list, item) {
+        (EvalResult::List(items), _) => Ok(items.iter().any(|candidate| same_value(candidate, item))),
+        (EvalResult::Map(entries), EvalResult::String(key)) => Ok(entries.contains_key(key)),
+        _ => fail!("El operador in necesita una lista o un mapa a la derecha"),
+    }
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::modules::tokenizer::tokenize;
-    use crate::modules::parser::Parser;
+// Valor de una clave de un mapa; si falta, el error indica la clave y dónde se buscó
+fn get_key(mut entries: BTreeMap<String, EvalResult>, key: &str, path: Option<String>) -> Result<EvalResult, ExprError> {
+    match entries.remove(key) {
+        Some(value) => Ok(value),
+        None => match path {
+            Some(path) => fail!("Clave no encontrada: \"{}\" en {}", key, path),
+            None => fail!("Clave no encontrada: \"{}\"", key),
+        },
+    }
+}
 
-    #[test]
-    fn test_evaluate_logics() {
-        let tokens = tokenize("true && false || !true");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+// Texto de una cadena de accesos (user.address["zip"]) para los mensajes de error
+fn describe(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Variable(name) => Some(name.to_string()),
+        Expr::Member(target, key) => describe(target).map(|path| format!("{}.{}", path, key)),
+        Expr::Index(target, index) => match index.as_ref() {
+            Expr::StringLiteral(key) => describe(target).map(|path| format!("{}[\"{}\"]", path, key)),
+            Expr::IntegerLiteral(n) => describe(target).map(|path| format!("{}[{}]", path, n)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-        let result = evaluate(expr);
-        assert_eq!(result, EvalResult::Bool(false)); // El resultado de "true && false || !true" es false
+// Política de NaN e infinito: ninguna operación los devuelve, se informa como error
+pub(crate) fn finite(value: f64, operation: impl FnOnce() -> String) -> Result<EvalResult, ExprError> {
+    match value.is_finite() {
+        true => Ok(EvalResult::Float(value)),
+        false => Err(ExprError::NotFinite(operation())),
     }
+}
 
-    #[test]
-    fn test_evaluate_logics_parentheses() {
-        let tokens = tokenize("!(true && false)");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+// Operandos de una operación en la que interviene un decimal exacto; None si no interviene ninguno.
+// Mezclarlo con un f64 es un error, porque el resultado ya no sería exacto.
+fn decimal_operands(left: &EvalResult, right: &EvalResult, op: &str) -> Result<Option<(Decimal, Decimal)>, ExprError> {
+    match (left, right) {
+        (EvalResult::Decimal(_), EvalResult::Float(_)) | (EvalResult::Float(_), EvalResult::Decimal(_)) => {
+            fail!("No se pueden mezclar decimales exactos y decimales de coma flotante con {}; usa decimal(x)", op)
+        }
+        (EvalResult::Decimal(_), _) | (_, EvalResult::Decimal(_)) => match (left.as_decimal(), right.as_decimal()) {
+            (Some(l), Some(r)) => Ok(Some((l, r))),
+            _ => fail!("Solo puedes operar números con {}", op),
+        },
+        _ => Ok(None),
+    }
+}
 
-        let result = evaluate(expr);
-        assert_eq!(result, EvalResult::Bool(true)); // El resultado de "!(true && false)" es true
+// Operaciones aritméticas con promoción: entero con entero da un entero (comprobando el
+// desbordamiento), con un decimal exacto el resultado es exacto y con un f64 es un f64
+fn arithmetic(
+    left: EvalResult,
+    right: EvalResult,
+    op: &str,
+    int_op: fn(i64, i64) -> Option<i64>,
+    decimal_op: fn(Decimal, Decimal) -> Option<Decimal>,
+    float_op: fn(f64, f64) -> f64,
+) -> Result<EvalResult, ExprError> {
+    // null se propaga antes de cualquier promoción: null + 1.5d es null, igual que null + 1
+    if left == EvalResult::Null || right == EvalResult::Null {
+        return Ok(EvalResult::Null);
+    }
+    #[cfg(feature = "bignum")]
+    if let Some((l, r)) = bignum::exact_operands(&left, &right, false) {
+        return bignum::operate(op, l, r);
     }
+    if let Some((l, r)) = decimal_operands(&left, &right, op)? {
+        return match decimal_op(l, r) {
+            Some(n) => Ok(EvalResult::Decimal(n)),
+            None => Err(ExprError::Overflow(format!("{} {} {}", left, op, right))),
+        };
+    }
+    match (&left, &right) {
+        (EvalResult::Int(l), EvalResult::Int(r)) => match int_op(*l, *r) {
+            Some(n) => Ok(EvalResult::Int(n)),
+            None => int_overflow(*l, op, *r),
+        },
+        _ => match (left.as_float(), right.as_float()) {
+            (Some(l), Some(r)) => finite(float_op(l, r), || format!("{} {} {}", left, op, right)),
+            _ => fail!("Solo puedes operar números con {}", op),
+        },
+    }
+}
 
-    #[test]
-    fn test_evaluate_logics_complex() {
-        let tokens = tokenize("!(true!=false)&&(!(true))");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
-        
-        let result = evaluate(expr);
-        assert_eq!(result, EvalResult::Bool(false)); // El resultado de "!(true != false) && (!(true))" es false
+// Con la característica bignum un resultado entero que no cabe en un i64 pasa a ser un entero grande
+fn int_overflow(left: i64, op: &str, right: i64) -> Result<EvalResult, ExprError> {
+    #[cfg(feature = "bignum")]
+    return bignum::operate(op, bignum::from_int(left), bignum::from_int(right));
+    #[cfg(not(feature = "bignum"))]
+    Err(ExprError::Overflow(format!("{} {} {}", left, op, right)))
+}
+
+// La división siempre da un decimal (7 / 2 es 3.5, 7d / 2 es 3.5d); dividir entre cero es un error
+pub(crate) fn divide(left: EvalResult, right: EvalResult) -> Result<EvalResult, ExprError> {
+    if left == EvalResult::Null || right == EvalResult::Null {
+        return Ok(EvalResult::Null);
+    }
+    if right.is_zero() && left.is_number() {
+        return Err(ExprError::DivisionByZero);
+    }
+    // Con la característica bignum la división de enteros es exacta: 1 / 3 es la fracción 1/3
+    #[cfg(feature = "bignum")]
+    if let Some((l, r)) = bignum::exact_operands(&left, &right, true) {
+        return bignum::operate("/", l, r);
+    }
+    if let Some((l, r)) = decimal_operands(&left, &right, "/")? {
+        return match l.checked_div(r) {
+            Some(n) => Ok(EvalResult::Decimal(n)),
+            None => Err(ExprError::Overflow(format!("{} / {}", left, right))),
+        };
     }
+    match (left.as_float(), right.as_float()) {
+        (Some(l), Some(r)) => finite(l / r, || format!("{} / {}", left, right)),
+        _ => fail!("Solo puedes operar números con /"),
+    }
+}
 
-    #[test]
-    fn test_evaluate_string_comparison_equal() {
-        let tokens = tokenize("\"hola\" == \"hola\"");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
-        
-        let result = evaluate(expr);
-        assert_eq!(result, EvalResult::Bool(true)); // "hola" == "hola" es true
+// // y % redondean el cociente hacia -infinito, como en Python: -7 // 2 es -4 y -7 % 2 es 1,
+// de forma que el resto tiene el signo del divisor (útil para repartir en grupos)
+fn floor_operation(
+    left: EvalResult,
+    right: EvalResult,
+    op: &str,
+    int_op: fn(i64, i64) -> Option<i64>,
+    decimal_op: fn(Decimal, Decimal) -> Option<Decimal>,
+    float_op: fn(f64, f64) -> f64,
+) -> Result<EvalResult, ExprError> {
+    if right.is_zero() && left.is_number() {
+        return Err(ExprError::DivisionByZero);
     }
+    arithmetic(left, right, op, int_op, decimal_op, float_op)
+}
 
-    #[test]
-    fn test_evaluate_string_comparison_not_equal() {
-        let tokens = tokenize("\"hola\" != \"mundo\"");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+fn floor_div(l: i64, r: i64) -> Option<i64> {
+    let quotient = l.checked_div(r)?;
+    match l % r != 0 && (l < 0) != (r < 0) {
+        true => Some(quotient - 1),
+        false => Some(quotient),
+    }
+}
 
-        let result = evaluate(expr);
-        assert_eq!(result, EvalResult::Bool(true)); // "hola" != "mundo" es true
+fn floor_mod(l: i64, r: i64) -> Option<i64> {
+    let remainder = l.checked_rem(r)?;
+    match remainder != 0 && (remainder < 0) != (r < 0) {
+        true => Some(remainder + r),
+        false => Some(remainder),
     }
+}
 
-    #[test]
-    fn test_evaluate_string_and_boolean_comparison() {
-        let tokens = tokenize("\"hola\" == true");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+// El resto de los decimales exactos es exacto; el cociente se obtiene a partir de él
+fn decimal_floor_mod(l: Decimal, r: Decimal) -> Option<Decimal> {
+    let remainder = l.checked_rem(r)?;
+    match !remainder.is_zero() && remainder.is_sign_negative() != r.is_sign_negative() {
+        true => remainder.checked_add(r),
+        false => Some(remainder),
+    }
+}
 
-        // Este caso debería hacer un pánico porque no se puede comparar un booleano con una cadena
-        let result = std::panic::catch_unwind(|| evaluate(expr));
-        assert!(result.is_err()); // Se espera un error
+// El cociente es entero y se deja con escala 0: -7.5d // 2 es -4, no -4.0
+fn decimal_floor_div(l: Decimal, r: Decimal) -> Option<Decimal> {
+    Some(l.checked_sub(decimal_floor_mod(l, r)?)?.checked_div(r)?.trunc())
+}
+
+fn float_floor_mod(l: f64, r: f64) -> f64 {
+    let remainder = l % r;
+    match remainder != 0.0 && (remainder < 0.0) != (r < 0.0) {
+        true => remainder + r,
+        false => remainder,
     }
+}
 
-    #[test]
-    fn test_evaluate_boolean_and_string() {
-        let tokens = tokenize("true == \"true\"");
-        let mut parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+// Potencia: entero ** entero no negativo es un entero, con exponente negativo es un decimal
+// (2 ** -1 es 0.5) y un decimal exacto solo se eleva a exponentes enteros
+pub(crate) fn power(base: EvalResult, exponent: EvalResult) -> Result<EvalResult, ExprError> {
+    if base.is_zero() && exponent.as_float().is_some_and(|e| e < 0.0) {
+        return Err(ExprError::DivisionByZero);
+    }
+    #[cfg(feature = "bignum")]
+    if let (Some((base, _)), EvalResult::Int(exponent)) = (bignum::exact_operands(&base, &exponent, false), &exponent) {
+        return bignum::power(base, *exponent);
+    }
+    match (&base, &exponent) {
+        (EvalResult::Null, _) | (_, EvalResult::Null) => Ok(EvalResult::Null),
+        (EvalResult::Int(b), EvalResult::Int(e)) if *e >= 0 => {
+            match u32::try_from(*e).ok().and_then(|e| b.checked_pow(e)) {
+                Some(n) => Ok(EvalResult::Int(n)),
+                #[cfg(feature = "bignum")]
+                None => bignum::power(bignum::from_int(*b), *e),
+                #[cfg(not(feature = "bignum"))]
+                None => Err(ExprError::Overflow(format!("{} ** {}", b, e))),
+            }
+        }
+        #[cfg(feature = "bignum")]
+        (EvalResult::Int(b), EvalResult::Int(e)) => bignum::power(bignum::from_int(*b), *e),
+        (EvalResult::Decimal(b), EvalResult::Int(e)) => match decimal_power(*b, *e) {
+            Some(n) => Ok(EvalResult::Decimal(n)),
+            None => Err(ExprError::Overflow(format!("{} ** {}", base, exponent))),
+        },
+        (EvalResult::Decimal(_), EvalResult::Float(_) | EvalResult::Decimal(_)) => {
+            fail!("Un decimal exacto solo se puede elevar a un exponente entero")
+        }
+        _ => match (base.as_float(), exponent.as_float()) {
+            (Some(b), Some(e)) => finite(b.powf(e), || format!("{} ** {}", base, exponent)),
+            _ => fail!("Solo puedes operar números con **"),
+        },
+    }
+}
 
-        // Este caso también debería hacer un pánico porque no se puede comparar un booleano con una cadena
-        let result = std::panic::catch_unwind(|| evaluate(expr));
-        assert!(result.is_err()); // Se espera un error
+// Potencia de un decimal exacto por multiplicaciones sucesivas (elevando al cuadrado)
+fn decimal_power(base: Decimal, exponent: i64) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    let mut square = base;
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.checked_mul(square)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = square.checked_mul(square)?;
+        }
     }
-    
-    #[test]
-    #[should_panic(expected = "Error: No se encontró un paréntesis de cierre")]
-    fn test_evaluate_invalid_parentheses() {
-        let tokens = tokenize("(true && false");
-        let mut parser = Parser::new(&tokens);
-        parser.parse().unwrap();
+    match exponent < 0 {
+        true => Decimal::ONE.checked_div(result),
+        false => Some(result),
+    }
+}
+
+fn negate(value: EvalResult) -> Result<EvalResult, ExprError> {
+    match value {
+        EvalResult::Null => Ok(EvalResult::Null),
+        EvalResult::Int(n) => match n.checked_neg() {
+            Some(negated) => Ok(EvalResult::Int(negated)),
+            #[cfg(feature = "bignum")]
+            None => Ok(bignum::normalize(-bignum::from_int(n))),
+            #[cfg(not(feature = "bignum"))]
+            None => Err(ExprError::Overflow(format!("-({})", n))),
+        },
+        EvalResult::Float(n) => Ok(EvalResult::Float(-n)),
+        EvalResult::Decimal(n) => Ok(EvalResult::Decimal(-n)),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(n) => Ok(bignum::normalize(-n)),
+        _ => fail!("Solo se puede negar un número"),
+    }
+}
+
+// Texto y expresión regular de =~ y captures; None si alguno es null.
+// Los patrones literales llegan ya compilados; los demás se compilan aquí.
+fn regex_operands(text: Expr, pattern: Expr, context: &mut Context) -> Result<Option<(String, CompiledRegex)>, ExprError> {
+    let text = match try_evaluate_with(text, context)? {
+        EvalResult::Null => return Ok(None),
+        EvalResult::String(text) => text,
+        other => fail!("Solo se pueden buscar expresiones regulares en textos: {:?}", other),
+    };
+    let regex = match pattern {
+        Expr::Regex(regex) => regex,
+        pattern => match try_evaluate_with(pattern, context)? {
+            EvalResult::Null => return Ok(None),
+            EvalResult::String(pattern) => CompiledRegex::try_new(&pattern).map_err(ExprError::Runtime)?,
+            other => fail!("El patrón de una expresión regular debe ser un texto: {:?}", other),
+        },
+    };
+    Ok(Some((text, regex)))
+}
+
+// like, ilike y glob: solo comparan textos
+fn wildcard_match(
+    text: EvalResult,
+    pattern: EvalResult,
+    op: &str,
+    compile: fn(&str) -> Result<Wildcard, String>,
+) -> Result<EvalResult, ExprError> {
+    match (text, pattern) {
+        (EvalResult::Null, _) | (_, EvalResult::Null) => Ok(EvalResult::Null),
+        (EvalResult::String(text), EvalResult::String(pattern)) => {
+            let wildcard = compile(&pattern).map_err(ExprError::Runtime)?;
+            Ok(EvalResult::Bool(wildcard.is_match(&text)))
+        }
+        _ => fail!("Solo se pueden comparar textos con {}", op),
+    }
+}
+
+// + suma números y concatena textos
+pub(crate) fn add(left: EvalResult, right: EvalResult) -> Result<EvalResult, ExprError> {
+    match (left, right) {
+        (EvalResult::String(l), EvalResult::String(r)) => Ok(EvalResult::String(l + &r)),
+        (left, right) => arithmetic(left, right, "+", i64::checked_add, Decimal::checked_add, |l, r| l + r),
+    }
+}
+
+// Evalúa comparaciones de orden entre dos números o dos textos (según el idioma del contexto)
+fn compare_values(
+    left: EvalResult,
+    right: EvalResult,
+    op: &str,
+    cmp: fn(Ordering) -> bool,
+    context: &mut Context,
+) -> Result<EvalResult, ExprError> {
+    let ordering = match (&left, &right) {
+        (EvalResult::Null, _) | (_, EvalResult::Null) => return Ok(EvalResult::Null),
+        (EvalResult::String(l), EvalResult::String(r)) => Some(context.text_order()?(l, r)),
+        _ if left.is_number() && right.is_number() => compare_numbers(&left, &right),
+        _ => fail!("Solo puedes comparar números o textos con {}", op),
+    };
+    Ok(EvalResult::Bool(ordering.is_some_and(cmp)))
+}
+
+// Evalúa los dos operandos de una operación binaria, de izquierda a derecha
+fn operands(left: Expr, right: Expr, context: &mut Context) -> Result<(EvalResult, EvalResult), ExprError> {
+    let left = try_evaluate_with(left, context)?;
+    let right = try_evaluate_with(right, context)?;
+    Ok((left, right))
+}
+
+// Un error de evaluación en las funciones que no devuelven Result termina con un pánico con su mensaje
+fn or_panic<T>(result: Result<T, ExprError>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+pub fn evaluate(expr: Expr) -> EvalResult {
+    evaluate_with(expr, &mut Context::new())
+}
+
+pub fn evaluate_with(expr: Expr, context: &mut Context) -> EvalResult {
+    or_panic(try_evaluate_with(expr, context))
+}
+
+// Evalúa las sentencias de un programa en orden y devuelve el valor de cada una.
+// Las definiciones de funciones no producen valor y quedan registradas en el contexto,
+// de forma que un preludio puede definirlas una vez para muchas reglas.
+pub fn evaluate_program(statements: Vec<Expr>, context: &mut Context) -> Vec<EvalResult> {
+    or_panic(try_evaluate_program(statements, context))
+}
+
+// Igual que evaluate, pero devuelve los errores de evaluación en lugar de terminar con un pánico
+pub fn try_evaluate(expr: Expr) -> Result<EvalResult, ExprError> {
+    try_evaluate_with(expr, &mut Context::new())
+}
+
+pub fn try_evaluate_program(statements: Vec<Expr>, context: &mut Context) -> Result<Vec<EvalResult>, ExprError> {
+    let outer = std::mem::take(&mut context.program);
+    let results = evaluate_statements(statements, context);
+    context.program = outer;
+    results
+}
+
+fn evaluate_statements(statements: Vec<Expr>, context: &mut Context) -> Result<Vec<EvalResult>, ExprError> {
+    let mut results = Vec::new();
+    for statement in statements {
+        match statement {
+            Expr::FunctionDef(name, params, body) => {
+                context.functions.insert(name, Function { params, body: *body, namespace: None });
+            }
+            Expr::Import(path, alias) => import_module(path, alias, context)?,
+            statement => results.push(try_evaluate_with(statement, context)?),
+        }
     }
+    Ok(results)
 }
 
+// Carga un módulo y registra sus funciones y constantes bajo su espacio de nombres (geo::distance)
+fn import_module(path: String, alias: Option<String>, context: &mut Context) -> Result<(), ExprError> {
+    let namespace = match alias {
+        Some(alias) => alias,
+        None => default_namespace(&path)?,
+    };
+    context.modules.enter(&path)?;
+    // Aunque la carga falle, el módulo deja de estar en la pila de importaciones
+    let result = context.modules.load(&path).and_then(|statements| {
+        let caller_scopes = std::mem::take(&mut context.scopes);
+        let caller_namespace = context.namespace.replace(namespace.clone());
+        let result = load_module_statements(statements, &namespace, context);
+        context.namespace = caller_namespace;
+        context.scopes = caller_scopes;
+        result
+    });
+    context.modules.leave();
+    result
+}
+
+fn load_module_statements(statements: Vec<Expr>, namespace: &str, context: &mut Context) -> Result<(), ExprError> {
+    for statement in statements {
+        match statement {
+            Expr::FunctionDef(name, params, body) => {
+                let function = Function { params, body: *body, namespace: Some(namespace.to_string()) };
+                context.functions.insert(format!("{}::{}", namespace, name), function);
+            }
+            Expr::Assign(name, boxed_value) => {
+                let value = try_evaluate_with(*boxed_value, context)?;
+                context.variables.insert(format!("{}::{}", namespace, name), value);
+            }
+            Expr::Import(path, alias) => import_module(path, alias, context)?,
+            statement => {
+                try_evaluate_with(statement, context)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Llama a una función definida por el usuario. El cuerpo solo ve sus parámetros, las asignaciones
+// del programa y las variables del contexto, no los let ni los parámetros de quien la llama.
+// Si no existe, se prueba con una lambda guardada en una variable y con las funciones integradas.
+fn call_function(name: String, args: Vec<Expr>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let function = match context.find_function(&name) {
+        Some(function) => function.clone(),
+        None => {
+            let callee = context.get(&name).cloned();
+            if !matches!(callee, Some(EvalResult::Closure(_))) {
+                if let Some(expr) = precompiled_regex_call(&name, &args) {
+                    return try_evaluate_with(expr, context);
+                }
+            }
+            let args = evaluate_all(args, context)?;
+            return match callee {
+                Some(EvalResult::Closure(closure)) => call_closure(&closure, args, context),
+                _ => match call_builtin(&name, args, context) {
+                    Some(result) => result,
+                    None => fail!("Función no definida: {}", name),
+                },
+            };
+        }
+    };
+    if function.params.len() != args.len() {
+        fail!(
+            "La función {} espera {} argumentos y recibió {}",
+            name, function.params.len(), args.len()
+        );
+    }
+    if context.call_depth >= context.max_call_depth {
+        fail!("Se superó la profundidad máxima de llamadas ({}) en {}", context.max_call_depth, name);
+    }
+
+    let scope: HashMap<String, EvalResult> = function.params.into_iter().zip(evaluate_all(args, context)?).collect();
+
+    let caller_scopes = std::mem::replace(&mut context.scopes, vec![scope]);
+    let caller_namespace = std::mem::replace(&mut context.namespace, function.namespace);
+    context.call_depth += 1;
+    let result = try_evaluate_with(function.body, context);
+    context.call_depth -= 1;
+    context.namespace = caller_namespace;
+    context.scopes = caller_scopes;
+    result
+}
+
+// matches(texto, "patrón") y captures(texto, "patrón") integrados, con el patrón que el parser ya compiló
+fn precompiled_regex_call(name: &str, args: &[Expr]) -> Option<Expr> {
+    let [text, pattern @ Expr::Regex(_)] = args else {
+        return None;
+    };
+    let (text, pattern) = (Box::new(text.clone()), Box::new(pattern.clone()));
+    match name {
+        "matches" => Some(Expr::RegexMatch(text, pattern)),
+        "captures" => Some(Expr::Captures(text, pattern)),
+        _ => None,
+    }
+}
+
+// Aplica una lambda a unos argumentos ya evaluados
+pub(crate) fn call_closure(closure: &Closure, args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    if closure.params.len() != args.len() {
+        fail!(
+            "La lambda espera {} argumentos y recibió {}",
+            closure.params.len(), args.len()
+        );
+    }
+    if context.call_depth >= context.max_call_depth {
+        fail!("Se superó la profundidad máxima de llamadas ({}) en una lambda", context.max_call_depth);
+    }
+
+    let params = closure.params.iter().cloned().zip(args).collect();
+    let caller_scopes = std::mem::replace(&mut context.scopes, vec![closure.captured.clone(), params]);
+    let caller_namespace = std::mem::replace(&mut context.namespace, closure.namespace.clone());
+    context.call_depth += 1;
+    let result = try_evaluate_with(closure.body.clone(), context);
+    context.call_depth -= 1;
+    context.namespace = caller_namespace;
+    context.scopes = caller_scopes;
+    result
+}
+
+// Evalúa una lista de expresiones en orden, parando en el primer error
+fn evaluate_all(exprs: Vec<Expr>, context: &mut Context) -> Result<Vec<EvalResult>, ExprError> {
+    exprs.into_iter().map(|expr| try_evaluate_with(expr, context)).collect()
+}
+
+// Evalúa una expresión. Cada grupo de operaciones se evalúa en su propia función para que
+// la pila de las llamadas recursivas no crezca con las variables de todos los casos.
+pub fn try_evaluate_with(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        // Literal booleano
+        Expr::BooleanLiteral(value) => EvalResult::Bool(value),
+
+        // String
+        Expr::StringLiteral(s) => EvalResult::String(s),
+
+        // Números
+        Expr::IntegerLiteral(n) => EvalResult::Int(n),
+        Expr::DecimalLiteral(n) => EvalResult::Decimal(n),
+        #[cfg(feature = "bignum")]
+        Expr::RationalLiteral(n) => bignum::normalize(n),
+        Expr::NumberLiteral(n) => EvalResult::Float(n),
+
+        // Nulo
+        Expr::NullLiteral => EvalResult::Null,
+
+        // Variable del contexto
+        Expr::Variable(name) => match context.get(&name) {
+            Some(value) => value.clone(),
+            None => match constant(&name) {
+                Some(value) => value,
+                None => fail!("Variable no definida: {}", name),
+            },
+        },
+
+        expr @ (Expr::Not(_) | Expr::And(..) | Expr::Or(..)) => return evaluate_logic(expr, context),
+
+        expr @ (Expr::Equal(..)
+        | Expr::NotEqual(..)
+        | Expr::CaselessEqual(..)
+        | Expr::Less(..)
+        | Expr::LessEqual(..)
+        | Expr::Greater(..)
+        | Expr::GreaterEqual(..)
+        | Expr::In(..)
+        | Expr::NotIn(..)
+        | Expr::IsNull(_)
+        | Expr::IsNotNull(_)) => return evaluate_comparison(expr, context),
+
+        expr @ (Expr::Negate(_)
+        | Expr::Add(..)
+        | Expr::Subtract(..)
+        | Expr::Multiply(..)
+        | Expr::Divide(..)
+        | Expr::IntDivide(..)
+        | Expr::Modulo(..)
+        | Expr::Power(..)) => return evaluate_arithmetic(expr, context),
+
+        expr @ (Expr::BitAnd(..)
+        | Expr::BitOr(..)
+        | Expr::BitXor(..)
+        | Expr::ShiftLeft(..)
+        | Expr::ShiftRight(..)
+        | Expr::BitNot(_)) => return evaluate_bitwise(expr, context),
+
+        expr @ (Expr::RegexMatch(..)
+        | Expr::Captures(..)
+        | Expr::Like(..)
+        | Expr::ILike(..)
+        | Expr::Glob(..)
+        | Expr::Interpolate(_)) => return evaluate_text(expr, context),
+
+        expr @ (Expr::Index(..) | Expr::Member(..) | Expr::SafeMember(..) | Expr::Coalesce(..)) => {
+            return evaluate_access(expr, context)
+        }
+
+        // Un patrón precompilado fuera de =~ se comporta como el texto literal que era
+        Expr::Regex(regex) => EvalResult::String(regex.as_str().to_string()),
+
+        // Operador ternario: solo se evalúa la rama elegida (una condición null no es cierta)
+        Expr::Conditional(boxed_cond, boxed_then, boxed_else) => {
+            let branch = match try_evaluate_with(*boxed_cond, context)? {
+                EvalResult::Bool(true) => boxed_then,
+                EvalResult::Bool(false) | EvalResult::Null => boxed_else,
+                _ => fail!("La condición del operador ternario debe ser booleana"),
+            };
+            return try_evaluate_with(*branch, context);
+        }
+
+        // let: el valor se evalúa una sola vez y solo es visible dentro del cuerpo
+        Expr::Let(name, boxed_value, boxed_body) => {
+            let value = try_evaluate_with(*boxed_value, context)?;
+            context.push_scope(name, value);
+            let result = try_evaluate_with(*boxed_body, context);
+            context.pop_scope();
+            return result;
+        }
+
+        // Asignación a una variable local del programa; devuelve el valor asignado
+        Expr::Assign(name, boxed_value) => {
+            let value = try_evaluate_with(*boxed_value, context)?;
+            context.assign(name, value.clone());
+            value
+        }
+
+        // Las funciones solo se definen como sentencias de un programa
+        Expr::FunctionDef(name, _, _) => {
+            fail!("La función {} solo se puede definir como sentencia de un programa", name)
+        }
+
+        // Llamada a función
+        Expr::Call(name, args) => return call_function(name, args, context),
+
+        // Lambda: captura las variables locales visibles en este punto
+        Expr::Lambda(params, boxed_body) => EvalResult::Closure(Rc::new(Closure {
+            params,
+            body: *boxed_body,
+            captured: context.capture_scopes(),
+            namespace: context.namespace.clone(),
+        })),
+
+        // Lista literal
+        Expr::ListLiteral(items) => EvalResult::List(evaluate_all(items, context)?),
+
+        // Los módulos solo se importan como sentencias de un programa
+        Expr::Import(path, _) => fail!("El módulo {} solo se puede importar como sentencia de un programa", path),
+
+        // case when: se evalúan las condiciones en orden y solo el resultado elegido
+        Expr::Case(branches, default) => return evaluate_case(branches, default, context),
+
+        // match: se devuelve el resultado del primer brazo con un patrón que coincida
+        Expr::Match(boxed_subject, arms) => return evaluate_match(*boxed_subject, arms, context),
+    };
+    Ok(result)
+}
+
+// NOT, AND y OR con lógica trivalente
+fn evaluate_logic(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        // Negación lógica (la negación de null es null)
+        Expr::Not(boxed_expr) => match try_evaluate_with(*boxed_expr, context)? {
+            EvalResult::Null => EvalResult::Null,
+            EvalResult::Bool(value) => EvalResult::Bool(!value),
+            EvalResult::String(_) => fail!("No se puede aplicar NOT a un String"),
+            _ => fail!("NOT solo se puede aplicar a booleanos"),
+        },
+
+        // Operación AND: false domina sobre null. Si la izquierda es false la derecha no se evalúa,
+        // para que x is not null && x.a == 1 proteja el acceso
+        Expr::And(boxed_left, boxed_right) => {
+            let left = logic_operand(try_evaluate_with(*boxed_left, context)?, "AND")?;
+            if left == Some(false) {
+                return Ok(EvalResult::Bool(false));
+            }
+            let right = logic_operand(try_evaluate_with(*boxed_right, context)?, "AND")?;
+
+            match (left, right) {
+                (_, Some(false)) => EvalResult::Bool(false),
+                (Some(true), Some(true)) => EvalResult::Bool(true),
+                _ => EvalResult::Null,
+            }
+        }
+
+        // Operación OR: true domina sobre null. Si la izquierda es true la derecha no se evalúa
+        Expr::Or(boxed_left, boxed_right) => {
+            let left = logic_operand(try_evaluate_with(*boxed_left, context)?, "OR")?;
+            if left == Some(true) {
+                return Ok(EvalResult::Bool(true));
+            }
+            let right = logic_operand(try_evaluate_with(*boxed_right, context)?, "OR")?;
+
+            match (left, right) {
+                (_, Some(true)) => EvalResult::Bool(true),
+                (Some(false), Some(false)) => EvalResult::Bool(false),
+                _ => EvalResult::Null,
+            }
+        }
+        _ => unreachable!("evaluate_logic solo recibe operaciones lógicas"),
+    };
+    Ok(result)
+}
+
+// Igualdades, comparaciones de orden, pertenencia y comprobaciones de nulos
+fn evaluate_comparison(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        // Igualdad (comparar con null da null; para eso está "is null")
+        Expr::Equal(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            match equals(left, right, "igualdad")? {
+                Some(equal) => EvalResult::Bool(equal),
+                None => EvalResult::Null,
+            }
+        }
+
+        // Desigualdad
+        Expr::NotEqual(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            match equals(left, right, "desigualdad")? {
+                Some(equal) => EvalResult::Bool(!equal),
+                None => EvalResult::Null,
+            }
+        }
+
+        // Igualdad de textos sin distinguir mayúsculas ni formas Unicode equivalentes
+        Expr::CaselessEqual(boxed_left, boxed_right) => match operands(*boxed_left, *boxed_right, context)? {
+            (EvalResult::Null, _) | (_, EvalResult::Null) => EvalResult::Null,
+            (EvalResult::String(l), EvalResult::String(r)) => EvalResult::Bool(caseless_eq(&l, &r)),
+            _ => fail!("Solo puedes comparar textos con ~="),
+        },
+
+        // Comparaciones de orden
+        Expr::Less(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            compare_values(left, right, "<", Ordering::is_lt, context)?
+        }
+        Expr::LessEqual(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            compare_values(left, right, "<=", Ordering::is_le, context)?
+        }
+        Expr::Greater(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            compare_values(left, right, ">", Ordering::is_gt, context)?
+        }
+        Expr::GreaterEqual(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            compare_values(left, right, ">=", Ordering::is_ge, context)?
+        }
+
+        // Pertenencia a una lista (la pertenencia de null es desconocida)
+        Expr::In(boxed_item, boxed_list) => match operands(*boxed_item, *boxed_list, context)? {
+            (EvalResult::Null, _) => EvalResult::Null,
+            (item, list) => EvalResult::Bool(contains(list, &item)?),
+        },
+        Expr::NotIn(boxed_item, boxed_list) => match operands(*boxed_item, *boxed_list, context)? {
+            (EvalResult::Null, _) => EvalResult::Null,
+            (item, list) => EvalResult::Bool(!contains(list, &item)?),
+        },
+
+        // Comprobaciones de nulos
+        Expr::IsNull(boxed_expr) => EvalResult::Bool(try_evaluate_with(*boxed_expr, context)? == EvalResult::Null),
+        Expr::IsNotNull(boxed_expr) => EvalResult::Bool(try_evaluate_with(*boxed_expr, context)? != EvalResult::Null),
+        _ => unreachable!("evaluate_comparison solo recibe comparaciones"),
+    };
+    Ok(result)
+}
+
+// Menos unario y operaciones aritméticas. Con una escala configurada en el contexto, los
+// decimales exactos se redondean tras cada operación.
+fn evaluate_arithmetic(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        Expr::Negate(boxed_expr) => negate(try_evaluate_with(*boxed_expr, context)?),
+        Expr::Add(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            add(left, right)
+        }
+        Expr::Subtract(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            arithmetic(left, right, "-", i64::checked_sub, Decimal::checked_sub, |l, r| l - r)
+        }
+        Expr::Multiply(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            arithmetic(left, right, "*", i64::checked_mul, Decimal::checked_mul, |l, r| l * r)
+        }
+        Expr::Divide(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            divide(left, right)
+        }
+        Expr::IntDivide(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            floor_operation(left, right, "//", floor_div, decimal_floor_div, |l, r| (l / r).floor())
+        }
+        Expr::Modulo(boxed_left, boxed_right) => {
+            let (left, right) = operands(*boxed_left, *boxed_right, context)?;
+            floor_operation(left, right, "%", floor_mod, decimal_floor_mod, float_floor_mod)
+        }
+        Expr::Power(boxed_base, boxed_exponent) => {
+            let (base, exponent) = operands(*boxed_base, *boxed_exponent, context)?;
+            power(base, exponent)
+        }
+        _ => unreachable!("evaluate_arithmetic solo recibe operaciones aritméticas"),
+    }?;
+    match (result, context.decimal_scale) {
+        (EvalResult::Decimal(n), Some((scale, rounding))) => Ok(EvalResult::Decimal(round(n, scale, rounding))),
+        (result, _) => Ok(result),
+    }
+}
+
+// Operaciones de bits, solo con enteros
+fn evaluate_bitwise(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let (left, right, op, int_op): (_, _, _, fn(i64, i64) -> Option<i64>) = match expr {
+        Expr::BitNot(boxed_expr) => {
+            return match try_evaluate_with(*boxed_expr, context)? {
+                EvalResult::Null => Ok(EvalResult::Null),
+                EvalResult::Int(n) => Ok(EvalResult::Int(!n)),
+                _ => fail!("Solo se puede aplicar ~ a enteros"),
+            };
+        }
+        Expr::BitAnd(left, right) => (left, right, "&", |l, r| Some(l & r)),
+        Expr::BitOr(left, right) => (left, right, "|", |l, r| Some(l | r)),
+        Expr::BitXor(left, right) => (left, right, "^", |l, r| Some(l ^ r)),
+        Expr::ShiftLeft(left, right) => (left, right, "<<", |l, r| l.checked_shl(u32::try_from(r).ok()?)),
+        Expr::ShiftRight(left, right) => (left, right, ">>", |l, r| l.checked_shr(u32::try_from(r).ok()?)),
+        _ => unreachable!("evaluate_bitwise solo recibe operaciones de bits"),
+    };
+    match operands(*left, *right, context)? {
+        (EvalResult::Null, _) | (_, EvalResult::Null) => Ok(EvalResult::Null),
+        (EvalResult::Int(l), EvalResult::Int(r)) => match int_op(l, r) {
+            // "<<" se desborda si pierde bits: deshaciendo el desplazamiento no vuelve a salir l
+            Some(n) if op == "<<" && n >> r != l => Err(ExprError::Overflow(format!("{} << {}", l, r))),
+            Some(n) => Ok(EvalResult::Int(n)),
+            None => fail!("El desplazamiento de {} debe estar entre 0 y 63 y es {}", op, r),
+        },
+        _ => fail!("Solo puedes operar enteros con {}", op),
+    }
+}
+
+// Expresiones regulares, comodines e interpolación de textos
+fn evaluate_text(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        // text =~ patrón
+        Expr::RegexMatch(boxed_text, boxed_pattern) => match regex_operands(*boxed_text, *boxed_pattern, context)? {
+            Some((text, regex)) => EvalResult::Bool(regex.is_match(&text)),
+            None => EvalResult::Null,
+        },
+        Expr::Captures(boxed_text, boxed_pattern) => match regex_operands(*boxed_text, *boxed_pattern, context)? {
+            Some((text, regex)) => capture_list(&regex, &text),
+            None => EvalResult::Null,
+        },
+
+        // Comodines de SQL y de shell
+        Expr::Like(boxed_text, boxed_pattern) => {
+            let (text, pattern) = operands(*boxed_text, *boxed_pattern, context)?;
+            wildcard_match(text, pattern, "like", |p| Ok(Wildcard::like(p, false)))?
+        }
+        Expr::ILike(boxed_text, boxed_pattern) => {
+            let (text, pattern) = operands(*boxed_text, *boxed_pattern, context)?;
+            wildcard_match(text, pattern, "ilike", |p| Ok(Wildcard::like(p, true)))?
+        }
+        Expr::Glob(boxed_text, boxed_pattern) => {
+            let (text, pattern) = operands(*boxed_text, *boxed_pattern, context)?;
+            wildcard_match(text, pattern, "glob", Wildcard::glob)?
+        }
+
+        // "Hola ${nombre}": se concatena el texto de cada parte
+        Expr::Interpolate(parts) => {
+            let text: String = evaluate_all(parts, context)?.iter().map(|part| part.to_string()).collect();
+            EvalResult::String(text)
+        }
+        _ => unreachable!("evaluate_text solo recibe operaciones de texto"),
+    };
+    Ok(result)
+}
+
+// Accesos a listas y mapas, navegación segura y valores por defecto
+fn evaluate_access(expr: Expr, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let result = match expr {
+        // Acceso por índice (listas) o por clave (mapas)
+        Expr::Index(boxed_target, boxed_index) => {
+            let path = describe(&boxed_target);
+            let (target, index) = operands(*boxed_target, *boxed_index, context)?;
+            index_value(target, index, path)?
+        }
+
+        // Acceso a miembro de un mapa: user.address
+        Expr::Member(boxed_target, key) => {
+            let path = describe(&boxed_target);
+            match try_evaluate_with(*boxed_target, context)? {
+                EvalResult::Map(entries) => get_key(entries, &key, path)?,
+                other => fail!("No se puede acceder a .{} de un valor que no es un mapa: {:?}", key, other),
+            }
+        }
+
+        // Navegación segura: null si el valor es null o si falta la clave
+        Expr::SafeMember(boxed_target, key) => match try_evaluate_with(*boxed_target, context)? {
+            EvalResult::Null => EvalResult::Null,
+            EvalResult::Map(mut entries) => entries.remove(&key).unwrap_or(EvalResult::Null),
+            other => fail!("No se puede acceder a ?.{} de un valor que no es un mapa: {:?}", key, other),
+        },
+
+        // x ?? valor: el valor por defecto solo se evalúa si x es null
+        Expr::Coalesce(boxed_value, boxed_default) => match try_evaluate_with(*boxed_value, context)? {
+            EvalResult::Null => try_evaluate_with(*boxed_default, context)?,
+            value => value,
+        },
+        _ => unreachable!("evaluate_access solo recibe accesos"),
+    };
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::tokenizer::tokenize;
+    use crate::modules::parser::Parser;
+
+    fn evaluate_str(input: &str, context: &mut Context) -> EvalResult {
+        let tokens = tokenize(input);
+        let mut parser = Parser::new(&tokens);
+        evaluate_with(parser.parse().unwrap(), context)
+    }
+
+    #[test]
+    fn test_evaluate_logics() {
+        let tokens = tokenize("true && false || !true");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::Bool(false)); // El resultado de "true && false || !true" es false
+    }
+
+    #[test]
+    fn test_evaluate_logics_parentheses() {
+        let tokens = tokenize("!(true && false)");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::Bool(true)); // El resultado de "!(true && false)" es true
+    }
+
+    #[test]
+    fn test_evaluate_logics_complex() {
+        let tokens = tokenize("!(true!=false)&&(!(true))");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+        
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::Bool(false)); // El resultado de "!(true != false) && (!(true))" es false
+    }
+
+    #[test]
+    fn test_evaluate_string_comparison_equal() {
+        let tokens = tokenize("\"hola\" == \"hola\"");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+        
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::Bool(true)); // "hola" == "hola" es true
+    }
+
+    #[test]
+    fn test_evaluate_string_comparison_not_equal() {
+        let tokens = tokenize("\"hola\" != \"mundo\"");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::Bool(true)); // "hola" != "mundo" es true
+    }
+
+    #[test]
+    fn test_evaluate_string_and_boolean_comparison() {
+        let tokens = tokenize("\"hola\" == true");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        // Este caso debería hacer un pánico porque no se puede comparar un booleano con una cadena
+        let result = std::panic::catch_unwind(|| evaluate(expr));
+        assert!(result.is_err()); // Se espera un error
+    }
+
+    #[test]
+    fn test_evaluate_boolean_and_string() {
+        let tokens = tokenize("true == \"true\"");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        // Este caso también debería hacer un pánico porque no se puede comparar un booleano con una cadena
+        let result = std::panic::catch_unwind(|| evaluate(expr));
+        assert!(result.is_err()); // Se espera un error
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error: No se encontró un paréntesis de cierre")]
+    fn test_evaluate_invalid_parentheses() {
+        let tokens = tokenize("(true && false");
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_evaluate_ternary() {
+        let tokens = tokenize(r#"!false ? "gold" : "standard""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::String("gold".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_ternary_is_lazy() {
+        // La rama no elegida haría pánico (NOT sobre un String) si se evaluase
+        let tokens = tokenize(r#"true ? "ok" : !"boom""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let result = evaluate(expr);
+        assert_eq!(result, EvalResult::String("ok".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "La condición del operador ternario debe ser booleana")]
+    fn test_evaluate_ternary_non_boolean_condition() {
+        let tokens = tokenize(r#""si" ? "a" : "b""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+        evaluate(expr);
+    }
+
+    #[test]
+    fn test_evaluate_number_comparisons() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("1 < 2", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("2 <= 2", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("-3 > 2", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("2.5 >= 2 == true", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("1.0 == 1", &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_variables() {
+        let mut context = Context::new();
+        context.set("vip", EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"vip ? "gold" : "standard""#, &mut context), EvalResult::String("gold".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable no definida: x")]
+    fn test_evaluate_undefined_variable() {
+        evaluate_str("x > 1", &mut Context::new());
+    }
+
+    #[test]
+    fn test_evaluate_if_else_if() {
+        let input = r#"if x > 10 then "big" else if x > 5 then "mid" else "small""#;
+        let mut context = Context::new();
+        context.set("x", EvalResult::Int(7));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::String("mid".to_string()));
+        context.set("x", EvalResult::Int(1));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::String("small".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_case_when() {
+        let input = r#"case when x > 10 then "big" when x > 5 then "mid" else "small" end"#;
+        let mut context = Context::new();
+        context.set("x", EvalResult::Int(12));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::String("big".to_string()));
+        context.set("x", EvalResult::Int(6));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::String("mid".to_string()));
+        context.set("x", EvalResult::Int(5));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::String("small".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_case_is_lazy() {
+        // Ni la condición ni el resultado posteriores al when elegido se evalúan
+        let input = r#"case when true then "ok" when undefined > 1 then !"boom" end"#;
+        assert_eq!(evaluate_str(input, &mut Context::new()), EvalResult::String("ok".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ninguna rama del case coincide y no hay else")]
+    fn test_evaluate_case_without_match() {
+        evaluate_str(r#"case when false then "a" end"#, &mut Context::new());
+    }
+
+    #[test]
+    fn test_evaluate_match() {
+        let input = r#"match status { "active" | "trial" => 1, "churned" => 0, _ => -1 }"#;
+        let mut context = Context::new();
+        context.set("status", EvalResult::String("trial".to_string()));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::Int(1));
+        context.set("status", EvalResult::String("churned".to_string()));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::Int(0));
+        context.set("status", EvalResult::String("unknown".to_string()));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::Int(-1));
+    }
+
+    #[test]
+    fn test_evaluate_match_numbers_and_booleans() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str(r#"match -2 { 1 | 2 => "pos", -2 => "neg", _ => "?" }"#, &mut context), EvalResult::String("neg".to_string()));
+        assert_eq!(evaluate_str(r#"match 1 > 0 { true => "si", false => "no" }"#, &mut context), EvalResult::String("si".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ningún brazo del match coincide con el valor Str(otro)")]
+    fn test_evaluate_match_without_match() {
+        evaluate_str(r#"match "otro" { "a" => 1 }"#, &mut Context::new());
+    }
+
+    #[test]
+    fn test_evaluate_arithmetic() {
+        let mut context = Context::new();
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(evaluate_str("1 + 2 * 3 - 4 / 2", &mut context), EvalResult::Float(5.0));
+        assert_eq!(evaluate_str("(1 + 2) * -3", &mut context), EvalResult::Int(-9));
+        assert_eq!(evaluate_str("10 - 4 - 3", &mut context), EvalResult::Int(3));
+        assert_eq!(evaluate_str("2 * 3 > 5", &mut context), EvalResult::Bool(true));
+    }
+
+    fn try_evaluate_str(input: &str, context: &mut Context) -> Result<EvalResult, ExprError> {
+        let tokens = tokenize(input);
+        let mut parser = Parser::new(&tokens);
+        try_evaluate_with(parser.parse().unwrap(), context)
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    fn test_evaluate_int_float_promotion() {
+        let mut context = Context::new();
+        // Los enteros grandes no pierden precisión (2^53 + 1 no cabe en un f64)
+        assert_eq!(evaluate_str("9007199254740992 + 1", &mut context), EvalResult::Int(9007199254740993));
+        assert_eq!(evaluate_str("2 * 1.5", &mut context), EvalResult::Float(3.0));
+        assert_eq!(evaluate_str("7 / 2", &mut context), EvalResult::Float(3.5));
+        // Un entero y un decimal con el mismo valor son iguales
+        assert_eq!(evaluate_str("3 == 3.0", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("2 < 2.5", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("2.0 in [1, 2]", &mut context), EvalResult::Bool(true));
+        assert_eq!(EvalResult::Float(3.0).to_string(), "3");
+    }
+
+    #[test]
+    fn test_evaluate_arithmetic_errors() {
+        let mut context = Context::new();
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(
+            try_evaluate_str("9223372036854775807 + 1", &mut context),
+            Err(ExprError::Overflow("9223372036854775807 + 1".to_string()))
+        );
+        assert_eq!(try_evaluate_str("10 / (5 - 5)", &mut context), Err(ExprError::DivisionByZero));
+        assert_eq!(try_evaluate_str("1.5 / 0.0", &mut context), Err(ExprError::DivisionByZero));
+        // Un resultado infinito o NaN es un error en lugar de propagarse
+        context.set("big", EvalResult::Float(f64::MAX));
+        assert_eq!(
+            try_evaluate_str("big * 10", &mut context),
+            Err(ExprError::NotFinite(format!("{} * 10", f64::MAX)))
+        );
+        assert_eq!(
+            try_evaluate_str("missing + 1", &mut context),
+            Err(ExprError::Runtime("Variable no definida: missing".to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: División entre cero")]
+    fn test_evaluate_division_by_zero_panics() {
+        evaluate_str("1 / 0", &mut Context::new());
+    }
+
+    fn decimal(value: &str) -> EvalResult {
+        EvalResult::Decimal(value.parse().unwrap())
+    }
+
+    #[test]
+    fn test_evaluate_decimals() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("0.1d + 0.2d", &mut context), decimal("0.3"));
+        // Con enteros el resultado sigue siendo exacto
+        assert_eq!(evaluate_str("19.99d * 3 - 1", &mut context), decimal("58.97"));
+        assert_eq!(evaluate_str("10d / 4", &mut context), decimal("2.5"));
+        assert_eq!(evaluate_str("-2.50d", &mut context), decimal("-2.50"));
+        assert_eq!(evaluate_str("2.50d == 2.5d && 3 == 3.0d && 1.5d < 2", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("match 2.0d { 1 => \"uno\", 2 => \"dos\", _ => \"otro\" }", &mut context), EvalResult::String("dos".to_string()));
+        assert_eq!(evaluate_str("round(2.675d, 2, \"half_even\")", &mut context), decimal("2.68"));
+        assert_eq!(evaluate_str("round(2.665d, 2, \"half_even\")", &mut context), decimal("2.66"));
+        assert_eq!(evaluate_str("decimal(\"19.99\") + decimal(0.01)", &mut context), decimal("20.00"));
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(
+            try_evaluate_str("1.5d + 0.5", &mut context),
+            Err(ExprError::Runtime(
+                "No se pueden mezclar decimales exactos y decimales de coma flotante con +; usa decimal(x)".to_string()
+            ))
+        );
+        assert_eq!(try_evaluate_str("1d / 0", &mut context), Err(ExprError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_evaluate_decimal_scale() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("10d / 3", &mut context), decimal("3.3333333333333333333333333333"));
+        context.set_decimal_scale(2, Rounding::HalfEven);
+        assert_eq!(evaluate_str("10d / 3", &mut context), decimal("3.33"));
+        assert_eq!(evaluate_str("0.125d * 1", &mut context), decimal("0.12"));
+        // Los enteros y los f64 no se ven afectados
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(evaluate_str("10 / 4", &mut context), EvalResult::Float(2.5));
+    }
+
+    #[test]
+    #[cfg(feature = "bignum")]
+    fn test_evaluate_exact_numbers() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("1/3 + 1/3 + 1/3 == 1", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("0.1 + 0.2 == 0.3", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("fraction(1 / 3 + 0.5)", &mut context).to_string(), "5/6");
+        // Los enteros no se desbordan y vuelven a ser Int cuando caben en un i64
+        assert_eq!(evaluate_str("9223372036854775807 + 1", &mut context).to_string(), "9223372036854775808");
+        assert_eq!(evaluate_str("-(-9223372036854775807 - 1)", &mut context).to_string(), "9223372036854775808");
+        assert_eq!(evaluate_str("2 * 9223372036854775807 - 9223372036854775807", &mut context), EvalResult::Int(i64::MAX));
+        assert_eq!(evaluate_str("123456789012345678901234567890 > 9223372036854775807", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("6 / 3", &mut context), EvalResult::Int(2));
+        assert_eq!(evaluate_str("fraction(1 / 4 + 0.25d)", &mut context).to_string(), "1/2");
+        // Solo una conversión explícita deja de ser exacta
+        assert_eq!(evaluate_str("float(1 / 4)", &mut context), EvalResult::Float(0.25));
+        assert_eq!(evaluate_str("round(2 / 3, 2)", &mut context), decimal("0.67"));
+        assert_eq!(evaluate_str("2 ** 64", &mut context).to_string(), "18446744073709551616");
+        assert_eq!(evaluate_str("fraction((2/3) ** -2)", &mut context).to_string(), "9/4");
+        assert_eq!(evaluate_str("fraction(7/2 // 1 + 7/2 % 1)", &mut context).to_string(), "7/2");
+        assert_eq!(try_evaluate_str("1 / (1/3 - 1/3)", &mut context), Err(ExprError::DivisionByZero));
+    }
+
+    #[test]
+    #[cfg(feature = "bignum")]
+    fn test_evaluate_tiny_fractions_are_not_zero() {
+        // 1/10^400 no cabe en un f64 (se queda en 0.0), pero no es cero
+        let mut context = Context::new();
+        let tiny = evaluate_str("1 / 10 ** 400", &mut context);
+        assert_eq!(tiny.as_float(), Some(0.0));
+        context.set("tiny", tiny);
+        assert_eq!(evaluate_str("2 / tiny == 2 * 10 ** 400", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("1 // tiny == 10 ** 400", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("1 % tiny", &mut context), EvalResult::Int(0));
+        assert_eq!(evaluate_str("tiny ** -1 == 10 ** 400", &mut context), EvalResult::Bool(true));
+        assert_eq!(try_evaluate_str("tiny / (tiny - tiny)", &mut context), Err(ExprError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_evaluate_extended_operators() {
+        let mut context = Context::new();
+        // Cociente y resto hacia -infinito: el resto tiene el signo del divisor
+        assert_eq!(evaluate_str("[7 // 2, -7 // 2, 7 % 3, -7 % 3, 7 % -3]", &mut context), integers(&[3, -4, 1, 2, -2]));
+        context.set("x", EvalResult::Float(7.5));
+        assert_eq!(evaluate_str("x // 2", &mut context), EvalResult::Float(3.0));
+        assert_eq!(evaluate_str("-x % 2", &mut context), EvalResult::Float(0.5));
+        assert_eq!(evaluate_str("10.5d % 3", &mut context), decimal("1.5"));
+        assert_eq!(evaluate_str("-10.5d // 3", &mut context), decimal("-4"));
+        for (input, expected) in [("-7.5d // 2", "-4"), ("7.5d // -2", "-4"), ("7.50d // 2.5d", "3"), ("-0.5d // 1", "-1")] {
+            assert_eq!(evaluate_str(input, &mut context).to_string(), expected, "{}", input);
+        }
+        context.set("user_id", EvalResult::Int(1234567));
+        assert_eq!(evaluate_str("user_id % 16", &mut context), EvalResult::Int(7));
+
+        assert_eq!(evaluate_str("2 ** 10", &mut context), EvalResult::Int(1024));
+        assert_eq!(evaluate_str("-2 ** 2", &mut context), EvalResult::Int(-4));
+        assert_eq!(evaluate_str("2 ** 3 ** 2", &mut context), EvalResult::Int(512));
+        assert_eq!(evaluate_str("1.1d ** 2", &mut context), decimal("1.21"));
+
+        // Operaciones de bits para comprobar flags
+        assert_eq!(evaluate_str("[6 & 3, 6 | 3, 6 ^ 3, ~0, 1 << 4, -16 >> 2]", &mut context), integers(&[2, 7, 5, -1, 16, -4]));
+        assert_eq!(evaluate_str("13 & 1 << 2 != 0", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("[-1 << 63, 1 << 62]", &mut context), integers(&[i64::MIN, 1 << 62]));
+        for input in ["1 << 63", "3 << 62", "-3 << 62"] {
+            assert!(matches!(try_evaluate_str(input, &mut context), Err(ExprError::Overflow(_))), "{}", input);
+        }
+        assert_eq!(evaluate_str("null & 1", &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    fn test_evaluate_float_power() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("2 ** -1", &mut context), EvalResult::Float(0.5));
+        assert_eq!(evaluate_str("9 ** 0.5", &mut context), EvalResult::Float(3.0));
+        assert_eq!(
+            try_evaluate_str("2 ** 63", &mut context),
+            Err(ExprError::Overflow("2 ** 63".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_extended_operator_errors() {
+        let mut context = Context::new();
+        assert_eq!(try_evaluate_str("7 % 0", &mut context), Err(ExprError::DivisionByZero));
+        assert_eq!(try_evaluate_str("7 // 0.0", &mut context), Err(ExprError::DivisionByZero));
+        assert_eq!(try_evaluate_str("0 ** -1", &mut context), Err(ExprError::DivisionByZero));
+        assert_eq!(
+            try_evaluate_str("1 << 64", &mut context),
+            Err(ExprError::Runtime("El desplazamiento de << debe estar entre 0 y 63 y es 64".to_string()))
+        );
+        assert_eq!(
+            try_evaluate_str("1.5 & 1", &mut context),
+            Err(ExprError::Runtime("Solo puedes operar enteros con &".to_string()))
+        );
+        assert_eq!(
+            try_evaluate_str("2d ** 0.5d", &mut context),
+            Err(ExprError::Runtime("Un decimal exacto solo se puede elevar a un exponente entero".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_let() {
+        let input = "let total = price * qty in total > 100 && total < 1000";
+        let mut context = Context::new();
+        context.set("price", EvalResult::Int(25));
+        context.set("qty", EvalResult::Int(6));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::Bool(true));
+        context.set("qty", EvalResult::Int(60));
+        assert_eq!(evaluate_str(input, &mut context), EvalResult::Bool(false));
+    }
+
+    #[test]
+    fn test_evaluate_let_multiple_bindings() {
+        let input = "let a = 2, b = a * 10 in a + b";
+        assert_eq!(evaluate_str(input, &mut Context::new()), EvalResult::Int(22));
+    }
+
+    #[test]
+    fn test_evaluate_let_shadowing() {
+        let mut context = Context::new();
+        context.set("x", EvalResult::Int(1));
+        // El let interno oculta al externo, que a su vez oculta a la variable del contexto
+        assert_eq!(evaluate_str("let x = x + 1 in let x = x * 10 in x", &mut context), EvalResult::Int(20));
+        // Fuera del let se vuelve a ver el valor original
+        assert_eq!(evaluate_str("(let x = 5 in x) + x", &mut context), EvalResult::Int(6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable no definida: y")]
+    fn test_evaluate_let_scope_ends_with_body() {
+        evaluate_str("(let y = 1 in y) + y", &mut Context::new());
+    }
+
+    #[test]
+    fn test_evaluate_program() {
+        let tokens = tokenize("subtotal = price * qty; total = subtotal + 5; total > 100");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        let mut context = Context::new();
+        context.set("price", EvalResult::Int(20));
+        context.set("qty", EvalResult::Int(5));
+        let results = evaluate_program(program, &mut context);
+        assert_eq!(results, vec![
+            EvalResult::Int(100),
+            EvalResult::Int(105),
+            EvalResult::Bool(true),
+        ]);
+        // Las variables locales del programa no quedan en el contexto
+        assert_eq!(context.get("total"), None);
+    }
+
+    #[test]
+    fn test_evaluate_program_reassignment() {
+        let tokens = tokenize("x = 1; x = x + 1; x");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        let results = evaluate_program(program, &mut Context::new());
+        assert_eq!(results.last(), Some(&EvalResult::Int(2)));
+    }
+
+    fn run_program(input: &str, context: &mut Context) -> Vec<EvalResult> {
+        let tokens = tokenize(input);
+        let mut parser = Parser::new(&tokens);
+        evaluate_program(parser.parse_program().unwrap(), context)
+    }
+
+    fn try_run_program(input: &str, context: &mut Context) -> Result<Vec<EvalResult>, ExprError> {
+        let tokens = tokenize(input);
+        let mut parser = Parser::new(&tokens);
+        try_evaluate_program(parser.parse_program().unwrap(), context)
+    }
+
+    #[test]
+    fn test_evaluate_function_prelude() {
+        let mut context = Context::new();
+        // El preludio solo define funciones, así que no produce valores
+        let prelude = "fn is_adult(a) = a >= 18; fn total(p, q) = p * q;";
+        assert_eq!(run_program(prelude, &mut context), vec![]);
+
+        context.set("edad", EvalResult::Int(20));
+        assert_eq!(evaluate_str("is_adult(edad)", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("is_adult(edad - 3)", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("total(2, 3) + total(1, 1)", &mut context), EvalResult::Int(7));
+    }
+
+    #[test]
+    fn test_evaluate_function_recursion() {
+        let mut context = Context::new();
+        let results = run_program("fn fact(n) = n <= 1 ? 1 : n * fact(n - 1); fact(5)", &mut context);
+        assert_eq!(results, vec![EvalResult::Int(120)]);
+    }
+
+    #[test]
+    fn test_evaluate_function_lexical_scope() {
+        let mut context = Context::new();
+        context.set("x", EvalResult::Int(1));
+        run_program("fn get_x() = x; fn add(x, y) = x + y;", &mut context);
+        // El let de quien llama no es visible dentro del cuerpo de la función
+        assert_eq!(evaluate_str("let x = 100 in get_x()", &mut context), EvalResult::Int(1));
+        // Los parámetros ocultan a las variables del contexto
+        assert_eq!(evaluate_str("add(10, x)", &mut context), EvalResult::Int(11));
+    }
+
+    #[test]
+    fn test_evaluate_function_sees_program_assignments() {
+        let mut context = Context::new();
+        let results = run_program("rate = 2; fn f(x) = x * rate; f(3)", &mut context);
+        assert_eq!(results, vec![EvalResult::Int(2), EvalResult::Int(6)]);
+        // Se resuelven al llamar, ocultan a las variables del contexto y no quedan en él
+        context.set("rate", EvalResult::Int(1));
+        let results = run_program("fn g() = rate * base; base = 10; rate = 3; g()", &mut context);
+        assert_eq!(results.last(), Some(&EvalResult::Int(30)));
+        assert_eq!(try_evaluate_str("g()", &mut context).unwrap_err().to_string(), "Error: Variable no definida: base");
+    }
+
+    #[test]
+    #[should_panic(expected = "Se superó la profundidad máxima de llamadas (10) en forever")]
+    fn test_evaluate_function_max_call_depth() {
+        let mut context = Context::new();
+        context.set_max_call_depth(10);
+        run_program("fn forever(n) = forever(n + 1); forever(0)", &mut context);
+    }
+
+    #[test]
+    #[should_panic(expected = "La función f espera 2 argumentos y recibió 1")]
+    fn test_evaluate_function_wrong_arity() {
+        run_program("fn f(a, b) = a + b; f(1)", &mut Context::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Función no definida: g")]
+    fn test_evaluate_undefined_function() {
+        evaluate_str("g(1)", &mut Context::new());
+    }
+
+    #[test]
+    fn test_evaluate_import_registered_module() {
+        let mut context = Context::new();
+        context.register_module(
+            "common/geo.rex",
+            "scale = 2; fn sq(x) = x * x; fn distance(a, b) = scale * sq(a - b);",
+        );
+        let results = run_program(r#"import "common/geo.rex"; geo::distance(5, 2) + geo::scale"#, &mut context);
+        assert_eq!(results, vec![EvalResult::Int(20)]);
+
+        // Las definiciones del módulo no quedan accesibles sin cualificar
+        let result = std::panic::catch_unwind(move || evaluate_str("sq(2)", &mut context));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_evaluate_import_with_alias_and_nested_imports() {
+        let mut context = Context::new();
+        context.register_module("math", "fn double(x) = x * 2;");
+        context.register_module("pricing", r#"import "math"; fn total(p) = math::double(p) + 1;"#);
+        let results = run_program(r#"import "pricing" as p; p::total(10)"#, &mut context);
+        assert_eq!(results, vec![EvalResult::Int(21)]);
+    }
+
+    #[test]
+    fn test_evaluate_import_module_from_file() {
+        let root = std::env::temp_dir().join(format!("rustexpresive_{}_import_module_from_file", std::process::id()));
+        std::fs::create_dir_all(root.join("common")).unwrap();
+        std::fs::write(root.join("common/tax.rex"), "rate = 0.25;\nfn apply(x) = x * (1 + rate);\n").unwrap();
+
+        let mut context = Context::new();
+        context.set_module_root(&root);
+        let results = run_program(r#"import "common/tax.rex"; tax::apply(100)"#, &mut context);
+        std::fs::remove_dir_all(&root).unwrap();
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(results, vec![EvalResult::Float(125.0)]);
+        #[cfg(feature = "bignum")]
+        assert_eq!(results, vec![EvalResult::Int(125)]);
+    }
+
+    #[test]
+    fn test_evaluate_import_errors() {
+        let mut context = Context::new();
+        context.register_module("a", r#"import "b";"#);
+        context.register_module("b", r#"import "a";"#);
+        assert_eq!(
+            try_run_program(r#"import "a";"#, &mut context),
+            Err(ExprError::Runtime("Importación cíclica: a -> b -> a".to_string()))
+        );
+        // El error no deja módulos a medio cargar: el contexto se puede volver a usar
+        context.register_module("b", "fn one() = 1;");
+        assert_eq!(try_run_program(r#"import "a"; b::one()"#, &mut context), Ok(vec![EvalResult::Int(1)]));
+        assert!(try_run_program(r#"import "no_existe.rex";"#, &mut context).is_err());
+    }
+
+    fn numbers(values: &[f64]) -> EvalResult {
+        EvalResult::List(values.iter().map(|n| EvalResult::Float(*n)).collect())
+    }
+
+    fn integers(values: &[i64]) -> EvalResult {
+        EvalResult::List(values.iter().map(|n| EvalResult::Int(*n)).collect())
+    }
+
+    #[test]
+    fn test_evaluate_higher_order_functions() {
+        let mut context = Context::new();
+        context.set("latencies", numbers(&[120.0, 80.0, 250.0, 40.0]));
+
+        assert_eq!(evaluate_str("any(latencies, x -> x > 200)", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("all(latencies, x -> x > 50)", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("filter(latencies, x -> x >= 100)", &mut context), numbers(&[120.0, 250.0]));
+        assert_eq!(evaluate_str("map(latencies, x -> x / 10)", &mut context), numbers(&[12.0, 8.0, 25.0, 4.0]));
+        assert_eq!(evaluate_str("count(latencies)", &mut context), EvalResult::Int(4));
+        assert_eq!(evaluate_str("count(latencies, x -> x < 100)", &mut context), EvalResult::Int(2));
+        assert_eq!(evaluate_str("sort_by(latencies, x -> -x)", &mut context), numbers(&[250.0, 120.0, 80.0, 40.0]));
+        assert_eq!(evaluate_str("count(filter(latencies, x -> x > 1000))", &mut context), EvalResult::Int(0));
+    }
+
+    #[test]
+    fn test_evaluate_sort_by_strings() {
+        let mut context = Context::new();
+        let names = ["pera", "kiwi", "manzana"].iter().map(|s| EvalResult::String(s.to_string())).collect();
+        context.set("names", EvalResult::List(names));
+        let sorted = ["kiwi", "manzana", "pera"].iter().map(|s| EvalResult::String(s.to_string())).collect();
+        assert_eq!(evaluate_str("sort_by(names, n -> n)", &mut context), EvalResult::List(sorted));
+    }
+
+    #[test]
+    fn test_evaluate_lambda_captures_locals() {
+        let mut context = Context::new();
+        context.set("items", numbers(&[1.0, 5.0, 10.0]));
+        // La lambda ve el let y el parámetro de la función donde se crea
+        run_program("fn above(list, limit) = filter(list, x -> x > limit);", &mut context);
+        assert_eq!(evaluate_str("let limit = 4 in count(items, x -> x > limit)", &mut context), EvalResult::Int(2));
+        assert_eq!(evaluate_str("above(items, 7)", &mut context), numbers(&[10.0]));
+    }
+
+    #[test]
+    fn test_evaluate_lambda_in_variable() {
+        let results = run_program("double = x -> x * 2; add = (a, b) -> a + b; add(double(3), 1)", &mut Context::new());
+        assert_eq!(results.last(), Some(&EvalResult::Int(7)));
+    }
+
+    #[test]
+    #[should_panic(expected = "La lambda de any debe devolver un booleano")]
+    fn test_evaluate_any_with_non_boolean_lambda() {
+        let mut context = Context::new();
+        context.set("items", numbers(&[1.0]));
+        evaluate_str("any(items, x -> x + 1)", &mut context);
+    }
+
+    #[test]
+    fn test_evaluate_membership() {
+        let mut context = Context::new();
+        context.set("country", EvalResult::String("PT".to_string()));
+        assert_eq!(evaluate_str(r#"country in ["ES", "PT", "FR"]"#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"country not in ["ES", "PT", "FR"]"#, &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str(r#"country in []"#, &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("[1, 2] in [[1, 2], [3]]", &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_list_literals_and_indexing() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("[1, 1 + 1, 3][1]", &mut context), EvalResult::Int(2));
+        assert_eq!(evaluate_str("[[1, 2], [3, 4]][1][0]", &mut context), EvalResult::Int(3));
+        assert_eq!(evaluate_str(r#"let l = ["a", "b"] in l[0] == "a""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("map([1, 2], x -> x * 10)", &mut context), integers(&[10, 20]));
+    }
+
+    #[test]
+    fn test_evaluate_list_equality() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("[1, 2, 3] == [1, 2, 3]", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("[1, 2, 3] == [1, 3, 2]", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str(r#"[1, "a"] != [1, "a", true]"#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("[] == []", &mut context), EvalResult::Bool(true));
+        // Los elementos se comparan como los valores sueltos: por valor numérico, sin importar el tipo
+        assert_eq!(evaluate_str("[1, 2] == [1.0, 2d]", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("[1d] == [1] && [[1]] == [[1.0]]", &mut context), EvalResult::Bool(true));
+        let map = |entries: &[(&str, EvalResult)]| {
+            EvalResult::Map(entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect())
+        };
+        context.set("a", map(&[("x", EvalResult::Int(1)), ("y", EvalResult::List(vec![EvalResult::Int(2)]))]));
+        context.set("b", map(&[("x", EvalResult::Float(1.0)), ("y", EvalResult::List(vec![EvalResult::Decimal(Decimal::TWO)]))]));
+        context.set("c", map(&[("z", EvalResult::Int(1)), ("y", EvalResult::List(vec![EvalResult::Int(2)]))]));
+        assert_eq!(evaluate_str("a == b && a != c", &mut context), EvalResult::Bool(true));
+        // Un null dentro da null salvo que otro par ya sea distinto
+        assert_eq!(evaluate_str("[null] == [null]", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("[1, null] != [1, null]", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("[1, null] == [2, null]", &mut context), EvalResult::Bool(false));
+        // in y match tratan igual los números dentro de listas, y ahí null coincide con null
+        assert_eq!(evaluate_str("[1] in [[1.0]] && [null] in [[null]]", &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Índice fuera de rango: 3 (longitud 2)")]
+    fn test_evaluate_index_out_of_range() {
+        evaluate_str("[1, 2][3]", &mut Context::new());
+    }
+
+    fn map(entries: &[(&str, EvalResult)]) -> EvalResult {
+        EvalResult::Map(entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+    }
+
+    fn user_context() -> Context {
+        let mut context = Context::new();
+        let address = map(&[("city", EvalResult::String("Madrid".to_string()))]);
+        context.set("user", map(&[("name", EvalResult::String("Ana".to_string())), ("address", address)]));
+        context.set("attrs", map(&[("x-flag", EvalResult::Bool(true))]));
+        context
+    }
+
+    #[test]
+    fn test_evaluate_member_access() {
+        let mut context = user_context();
+        assert_eq!(evaluate_str(r#"user.address.city == "Madrid""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"attrs["x-flag"]"#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"user["address"].city"#, &mut context), EvalResult::String("Madrid".to_string()));
+        assert_eq!(evaluate_str(r#""x-flag" in attrs && "y" not in attrs"#, &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_member_access_in_lambdas() {
+        let mut context = Context::new();
+        let items = vec![
+            map(&[("price", EvalResult::Int(50))]),
+            map(&[("price", EvalResult::Int(150))]),
+        ];
+        context.set("items", EvalResult::List(items));
+        assert_eq!(evaluate_str("any(items, x -> x.price > 100)", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("map(items, x -> x.price)[1]", &mut context), EvalResult::Int(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "Clave no encontrada: \"zip\" en user.address")]
+    fn test_evaluate_missing_key() {
+        evaluate_str("user.address.zip", &mut user_context());
+    }
+
+    #[test]
+    #[should_panic(expected = "Clave no encontrada: \"y-flag\" en attrs")]
+    fn test_evaluate_missing_key_with_brackets() {
+        evaluate_str(r#"attrs["y-flag"]"#, &mut user_context());
+    }
+
+    // NULOS: lógica trivalente de Kleene, como en SQL
+    #[test]
+    fn test_evaluate_null_literal() {
+        assert_eq!(evaluate_str("null", &mut Context::new()), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_kleene_and() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("true && null", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("null && true", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("false && null", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("null && false", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("null && null", &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_kleene_or() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("true || null", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("null || true", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("false || null", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("null || false", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("null || null", &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_kleene_not() {
+        assert_eq!(evaluate_str("!null", &mut Context::new()), EvalResult::Null);
+        assert_eq!(evaluate_str("!(false || null)", &mut Context::new()), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_null_propagation() {
+        // Comparar u operar con null da null, como en SQL; null == null también es null
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("null == null", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str(r#""a" != null"#, &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("null > 1", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("1 + null * 2", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("-null", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("null in [1, null]", &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_null_propagation_with_decimals() {
+        let mut context = Context::new();
+        context.set("discount", EvalResult::Null);
+        assert_eq!(evaluate_str("null + 1.5d", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("1.5d + null", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("19.99d * discount", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("10d / discount", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("10d % discount", &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_null_conditions_are_not_true() {
+        // En ternarios, if y case when una condición null se trata como no cierta
+        let mut context = Context::new();
+        assert_eq!(evaluate_str(r#"null ? "si" : "no""#, &mut context), EvalResult::String("no".to_string()));
+        assert_eq!(evaluate_str(r#"if null > 1 then "si" else "no""#, &mut context), EvalResult::String("no".to_string()));
+        assert_eq!(evaluate_str(r#"case when null then 1 else 2 end"#, &mut context), EvalResult::Int(2));
+        assert_eq!(evaluate_str("match null { null => 1, _ => 2 }", &mut context), EvalResult::Int(1));
+    }
+
+    #[test]
+    fn test_evaluate_is_null() {
+        let mut context = Context::new();
+        context.set("missing", EvalResult::Null);
+        assert_eq!(evaluate_str("missing is null", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("missing is not null", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("(1 + null) is null && 1 is not null", &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_null_guards_short_circuit() {
+        // Si la izquierda decide el resultado, la derecha no se evalúa y el acceso queda protegido
+        let mut context = Context::new();
+        assert_eq!(evaluate_str("let x = null in x is not null && x.a == 1", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("let x = null in x is null || x.a == 1", &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str("false && undefined_variable", &mut context), EvalResult::Bool(false));
+        assert_eq!(evaluate_str("true || undefined_variable", &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_coalesce() {
+        let mut context = Context::new();
+        context.set("discount", EvalResult::Null);
+        assert_eq!(evaluate_str("discount ?? 0", &mut context), EvalResult::Int(0));
+        assert_eq!(evaluate_str("5 ?? undefined_variable", &mut context), EvalResult::Int(5));
+        assert_eq!(evaluate_str("null ?? discount ?? 10", &mut context), EvalResult::Int(10));
+        assert_eq!(evaluate_str("discount ?? 0 > 5", &mut context), EvalResult::Bool(false));
+    }
+
+    #[test]
+    fn test_evaluate_safe_navigation() {
+        let mut context = user_context();
+        context.set("nobody", EvalResult::Null);
+        assert_eq!(evaluate_str("user?.address?.city", &mut context), EvalResult::String("Madrid".to_string()));
+        assert_eq!(evaluate_str("user?.address?.zip", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str("nobody?.address?.city", &mut context), EvalResult::Null);
+        assert_eq!(evaluate_str(r#"nobody?.name ?? "anónimo""#, &mut context), EvalResult::String("anónimo".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_interpolation() {
+        let mut context = user_context();
+        context.set("amount", EvalResult::Float(12.5));
+        context.set("tags", EvalResult::List(vec![EvalResult::String("a".to_string()), EvalResult::Null]));
+        assert_eq!(
+            evaluate_str(r#""Hello ${user.name}, you owe ${amount}""#, &mut context),
+            EvalResult::String("Hello Ana, you owe 12.5".to_string())
+        );
+        assert_eq!(
+            evaluate_str(r#""${user.address.city == "Madrid" ? "local" : "remoto"}: ${tags}""#, &mut context),
+            EvalResult::String("local: [a, null]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_string_concat_and_functions() {
+        let mut context = user_context();
+        assert_eq!(
+            evaluate_str(r#"upper(user.name) + " (" + substr(user.address.city, 0, 3) + ")""#, &mut context),
+            EvalResult::String("ANA (Mad)".to_string())
+        );
+        assert_eq!(
+            evaluate_str(r#"starts_with(user.name, "A") && len(trim("  Ñandú ")) == 5"#, &mut context),
+            EvalResult::Bool(true)
+        );
+        assert_eq!(evaluate_str(r#"user?.email + "!""#, &mut context), EvalResult::Null);
+        assert_eq!(
+            evaluate_str(r#"split(replace("a-b-c", "-", ","), ",")"#, &mut context),
+            EvalResult::List(vec!["a", "b", "c"].into_iter().map(|s| EvalResult::String(s.to_string())).collect())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_regex() {
+        let mut context = Context::new();
+        context.set("email", EvalResult::String("ana@corp.com".to_string()));
+        context.set("pattern", EvalResult::String(r"^\d{4}$".to_string()));
+        assert_eq!(evaluate_str(r#"email =~ r"^[^@]+@corp\.com$""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"matches("12a", pattern) || "2024" =~ pattern"#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"null =~ "x""#, &mut Context::new()), EvalResult::Null);
+        assert_eq!(
+            evaluate_str(r#"captures(email, r"^(\w+)@(\w+)")"#, &mut context),
+            EvalResult::List(vec!["ana@corp", "ana", "corp"].into_iter().map(|s| EvalResult::String(s.to_string())).collect())
+        );
+        assert_eq!(evaluate_str(r#"captures("x", "y")"#, &mut context), EvalResult::List(vec![]));
+    }
+
+    #[test]
+    fn test_evaluate_user_functions_shadow_regex_builtins() {
+        let mut context = Context::new();
+        let results = run_program(r#"fn matches(a, b) = 1; fn captures(a, b) = [a]; [matches("a", "b"), captures("x", "y")]"#, &mut context);
+        let expected = EvalResult::List(vec![EvalResult::Int(1), EvalResult::List(vec![EvalResult::String("x".to_string())])]);
+        assert_eq!(results.last(), Some(&expected));
+
+        let mut context = Context::new();
+        context.set("matches", evaluate_str("(a, b) -> a == b", &mut Context::new()));
+        assert_eq!(evaluate_str(r#"matches("b", "b")"#, &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    fn test_evaluate_like_and_glob() {
+        let mut context = user_context();
+        context.set("path", EvalResult::String("/var/log/app.log".to_string()));
+        assert_eq!(evaluate_str(r#"user.name like "A_a" && user.name not like "a%""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"user.address.city ilike "mad%""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"path glob "/var/log/*.log""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"user?.email like "%@corp.com""#, &mut context), EvalResult::Null);
+    }
+
+    #[test]
+    fn test_evaluate_caseless_equality_and_collation() {
+        let mut context = Context::new();
+        context.set("pais", EvalResult::String("ESPAÑA".to_string()));
+        context.set("cafe", EvalResult::String("Cafe\u{301}".to_string()));
+        assert_eq!(evaluate_str(r#"pais ~= "españa" && eq_ci(cafe, "CAFÉ")"#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"cafe == "Café" || normalize(cafe) == "Café""#, &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    #[cfg(feature = "collation")]
+    fn test_evaluate_collation_by_locale() {
+        let mut context = Context::new();
+        assert_eq!(evaluate_str(r#""ñu" < "oso" && "árbol" < "burro""#, &mut context), EvalResult::Bool(true));
+
+        // En sueco la ä va después de la z
+        assert_eq!(evaluate_str(r#""ära" > "zeta""#, &mut context), EvalResult::Bool(false));
+        context.set_locale("sv").unwrap();
+        assert_eq!(evaluate_str(r#""ära" > "zeta""#, &mut context), EvalResult::Bool(true));
+        // Un idioma no válido se devuelve como error y no cambia el orden
+        assert!(context.set_locale("no es un idioma").is_err());
+        assert_eq!(evaluate_str(r#""ära" > "zeta""#, &mut context), EvalResult::Bool(true));
+    }
+
+    #[test]
+    #[cfg(not(feature = "collation"))]
+    fn test_evaluate_collation_by_code_point() {
+        // Sin ICU la ñ va después de la z y las mayúsculas antes que las minúsculas
+        let mut context = Context::new();
+        assert_eq!(evaluate_str(r#""ñu" > "zeta" && "Zeta" < "arbol""#, &mut context), EvalResult::Bool(true));
+        assert_eq!(evaluate_str(r#"sort_by(["oso", "ñu", "Oso"], x -> x)"#, &mut context).to_string(), "[Oso, oso, ñu]");
+    }
+}
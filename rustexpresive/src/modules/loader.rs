@@ -0,0 +1,183 @@
+use crate::modules::error::{fail, ExprError};
+use crate::modules::parser::{Expr, Parser};
+use crate::modules::tokenizer::try_tokenize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Resuelve, analiza y guarda en caché los módulos que se importan con import "ruta"
+#[derive(Default)]
+pub struct ModuleLoader {
+    // Módulos registrados por el host, por nombre
+    sources: HashMap<String, String>,
+    // Carpeta desde la que se resuelven las rutas que no están registradas; sin ella solo se
+    // pueden importar módulos registrados
+    root: Option<PathBuf>,
+    // Programas ya analizados, por ruta
+    cache: HashMap<String, Vec<Expr>>,
+    // Módulos que se están cargando, del primero al último, para detectar ciclos
+    loading: Vec<String>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        ModuleLoader::default()
+    }
+
+    // Registra el código de un módulo; tiene prioridad sobre los ficheros
+    pub fn register(&mut self, name: &str, source: &str) {
+        self.sources.insert(name.to_string(), source.to_string());
+        self.cache.remove(name);
+    }
+
+    pub fn set_root(&mut self, root: impl Into<PathBuf>) {
+        self.root = Some(root.into());
+    }
+
+    // Marca el inicio de la carga de un módulo y detecta importaciones cíclicas
+    pub fn enter(&mut self, path: &str) -> Result<(), ExprError> {
+        if self.loading.iter().any(|loading| loading == path) {
+            let mut chain = self.loading.clone();
+            chain.push(path.to_string());
+            fail!("Importación cíclica: {}", chain.join(" -> "));
+        }
+        self.loading.push(path.to_string());
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.loading.pop();
+    }
+
+    // Devuelve las sentencias del módulo, analizándolo solo la primera vez
+    pub fn load(&mut self, path: &str) -> Result<Vec<Expr>, ExprError> {
+        if let Some(statements) = self.cache.get(path) {
+            return Ok(statements.clone());
+        }
+
+        let source = match self.sources.get(path) {
+            Some(source) => source.clone(),
+            None => {
+                let file = self.resolve(path)?;
+                match fs::read_to_string(&file) {
+                    Ok(source) => source,
+                    Err(err) => fail!("No se pudo cargar el módulo {}: {}", path, err),
+                }
+            }
+        };
+
+        let tokens = try_tokenize(&source)?;
+        let statements = Parser::new(&tokens).try_parse_program()?;
+        self.cache.insert(path.to_string(), statements.clone());
+        Ok(statements)
+    }
+
+    // Fichero de un módulo dentro de la carpeta raíz. Se rechazan las rutas absolutas, los ".." y
+    // los enlaces que llevan fuera de ella, y sin carpeta raíz no se lee ningún fichero.
+    fn resolve(&self, path: &str) -> Result<PathBuf, ExprError> {
+        let Some(root) = &self.root else {
+            fail!("No existe el módulo {}: regístralo o indica la carpeta de módulos con set_module_root", path);
+        };
+        if !Path::new(path).components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir)) {
+            fail!("El módulo {} está fuera de la carpeta de módulos", path);
+        }
+        let root = match root.canonicalize() {
+            Ok(root) => root,
+            Err(err) => fail!("No se pudo abrir la carpeta de módulos {}: {}", root.display(), err),
+        };
+        let file = match root.join(path).canonicalize() {
+            Ok(file) => file,
+            Err(err) => fail!("No se pudo cargar el módulo {}: {}", path, err),
+        };
+        if !file.starts_with(&root) {
+            fail!("El módulo {} está fuera de la carpeta de módulos", path);
+        }
+        Ok(file)
+    }
+}
+
+// Espacio de nombres por defecto de un módulo: el nombre del fichero sin extensión
+pub fn default_namespace(path: &str) -> Result<String, ExprError> {
+    match Path::new(path).file_stem() {
+        Some(stem) => Ok(stem.to_string_lossy().to_string()),
+        None => fail!("No se puede deducir el espacio de nombres del módulo {}; usa import \"{}\" as nombre", path, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_namespace() {
+        assert_eq!(default_namespace("common/geo.rex").unwrap(), "geo");
+        assert_eq!(default_namespace("pricing").unwrap(), "pricing");
+        assert!(default_namespace("..").is_err());
+    }
+
+    #[test]
+    fn test_load_registered_module_is_cached() {
+        let mut loader = ModuleLoader::new();
+        loader.register("geo", "fn sq(x) = x * x;");
+        assert!(!loader.cache.contains_key("geo"));
+
+        let statements = loader.load("geo").unwrap();
+        assert_eq!(statements.len(), 1);
+        assert!(loader.cache.contains_key("geo"));
+
+        // Registrar de nuevo el módulo invalida la caché
+        loader.register("geo", "fn sq(x) = x * x; fn cube(x) = x * x * x;");
+        assert!(!loader.cache.contains_key("geo"));
+        assert_eq!(loader.load("geo").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_load_module_from_root() {
+        // Carpeta propia del proceso y del test para que ejecuciones simultáneas no se pisen
+        let root = std::env::temp_dir().join(format!("rustexpresive_{}_load_module_from_root", std::process::id()));
+        fs::create_dir_all(root.join("common")).unwrap();
+        fs::write(root.join("common/geo.rex"), "radius = 6371;\nfn sq(x) = x * x;\n").unwrap();
+
+        let mut loader = ModuleLoader::new();
+        loader.set_root(&root);
+        assert_eq!(loader.load("common/geo.rex").unwrap().len(), 2);
+
+        // Una vez en caché, el fichero ya no se vuelve a leer
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(loader.load("common/geo.rex").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_load_missing_module() {
+        let mut loader = ModuleLoader::new();
+        loader.set_root(std::env::temp_dir());
+        let error = loader.load("no/existe.rex").unwrap_err();
+        assert!(error.to_string().starts_with("Error: No se pudo cargar el módulo no/existe.rex"), "{}", error);
+    }
+
+    #[test]
+    fn test_load_stays_inside_root() {
+        let mut loader = ModuleLoader::new();
+        let error = loader.load("common/geo.rex").unwrap_err();
+        assert!(error.to_string().starts_with("Error: No existe el módulo common/geo.rex"), "{}", error);
+
+        loader.set_root(std::env::temp_dir().join("modules"));
+        for path in ["/etc/passwd", "../secret.rex", "common/../../secret.rex"] {
+            assert_eq!(
+                loader.load(path),
+                Err(ExprError::Runtime(format!("El módulo {} está fuera de la carpeta de módulos", path)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let mut loader = ModuleLoader::new();
+        loader.enter("a").unwrap();
+        loader.enter("b").unwrap();
+        assert_eq!(
+            loader.enter("a"),
+            Err(ExprError::Runtime("Importación cíclica: a -> b -> a".to_string()))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use regex::Regex;
+use std::fmt;
+
+// Expresión regular ya compilada. Los patrones literales se compilan una sola vez al
+// analizar la expresión y viajan dentro del Expr.
+#[derive(Clone)]
+pub struct CompiledRegex(Regex);
+
+impl CompiledRegex {
+    // Compila el patrón; el parser convierte el error de un patrón literal en un error de sintaxis
+    // y el intérprete el de un patrón que se conoce al evaluar en un error de ejecución
+    pub fn try_new(pattern: &str) -> Result<Self, String> {
+        match Regex::new(pattern) {
+            Ok(regex) => Ok(CompiledRegex(regex)),
+            Err(err) => Err(format!("Expresión regular no válida \"{}\": {}", pattern, err)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    // Texto completo de la coincidencia seguido de cada grupo; None en los grupos que no participan
+    pub fn captures(&self, text: &str) -> Option<Vec<Option<String>>> {
+        let captures = self.0.captures(text)?;
+        Some(captures.iter().map(|group| group.map(|m| m.as_str().to_string())).collect())
+    }
+}
+
+// Dos expresiones regulares son iguales si tienen el mismo patrón
+impl PartialEq for CompiledRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl fmt::Debug for CompiledRegex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r\"{}\"", self.as_str())
+    }
+}
+
+// Pieza de un patrón de comodines de like o glob
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Char(char),
+    // Un carácter cualquiera; en glob no cruza '/'
+    One { slash: bool },
+    // Cualquier secuencia, también vacía; en glob "*" no cruza '/' y "**" sí
+    Many { slash: bool },
+    // [abc], [a-z] o [!a-z]
+    Class(Vec<(char, char)>, bool),
+}
+
+impl Piece {
+    // Si la pieza consume este carácter y avanza
+    fn accepts(&self, ch: char) -> bool {
+        match self {
+            Piece::Char(c) => *c == ch,
+            Piece::One { slash } => *slash || ch != '/',
+            Piece::Many { .. } => false,
+            Piece::Class(ranges, negated) => ranges.iter().any(|(from, to)| (*from..=*to).contains(&ch)) != *negated,
+        }
+    }
+}
+
+// Patrón de comodines de SQL (like, ilike) o de shell (glob). Se compara sin motor de
+// expresiones regulares, simulando a la vez todas las posiciones posibles del patrón, así
+// que el coste es como mucho texto × patrón y nunca se dispara con muchos "%".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wildcard {
+    pieces: Vec<Piece>,
+    case_insensitive: bool,
+}
+
+impl Wildcard {
+    // % es cualquier secuencia y _ un carácter; \ escapa el siguiente
+    pub fn like(pattern: &str, case_insensitive: bool) -> Self {
+        let mut pieces = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '%' => pieces.push(Piece::Many { slash: true }),
+                '_' => pieces.push(Piece::One { slash: true }),
+                '\\' => pieces.push(Piece::Char(chars.next().unwrap_or('\\'))),
+                ch => pieces.push(Piece::Char(ch)),
+            }
+        }
+        Wildcard::new(pieces, case_insensitive)
+    }
+
+    // * es cualquier secuencia dentro de un segmento de ruta, ** cruza '/', ? es un carácter
+    // y [a-z] o [!a-z] una clase; \ escapa el siguiente
+    pub fn glob(pattern: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    pieces.push(Piece::Many { slash: true });
+                }
+                '*' => pieces.push(Piece::Many { slash: false }),
+                '?' => pieces.push(Piece::One { slash: false }),
+                '[' => {
+                    let negated = chars.next_if(|ch| *ch == '!' || *ch == '^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') if !ranges.is_empty() => break,
+                            Some(from) => match chars.next_if_eq(&'-') {
+                                Some(_) if chars.peek().is_some_and(|to| *to != ']') => {
+                                    ranges.push((from, chars.next().unwrap()));
+                                }
+                                Some(_) => ranges.extend([(from, from), ('-', '-')]),
+                                None => ranges.push((from, from)),
+                            },
+                            None => return Err(format!("Falta ']' en el patrón glob \"{}\"", pattern)),
+                        }
+                    }
+                    pieces.push(Piece::Class(ranges, negated));
+                }
+                '\\' => pieces.push(Piece::Char(chars.next().unwrap_or('\\'))),
+                ch => pieces.push(Piece::Char(ch)),
+            }
+        }
+        Ok(Wildcard::new(pieces, false))
+    }
+
+    fn new(pieces: Vec<Piece>, case_insensitive: bool) -> Self {
+        let pieces = match case_insensitive {
+            true => pieces
+                .into_iter()
+                .flat_map(|piece| match piece {
+                    Piece::Char(ch) => ch.to_lowercase().map(Piece::Char).collect(),
+                    piece => vec![piece],
+                })
+                .collect(),
+            false => pieces,
+        };
+        Wildcard { pieces, case_insensitive }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = match self.case_insensitive {
+            true => text.to_lowercase(),
+            false => text.to_string(),
+        };
+        // states[i]: el texto leído hasta ahora encaja con las primeras i piezas
+        let mut states = vec![false; self.pieces.len() + 1];
+        states[0] = true;
+        self.skip_empty(&mut states);
+        for ch in text.chars() {
+            let mut next = vec![false; states.len()];
+            for (i, piece) in self.pieces.iter().enumerate().filter(|(i, _)| states[*i]) {
+                match piece {
+                    Piece::Many { slash } if *slash || ch != '/' => next[i] = true,
+                    piece if piece.accepts(ch) => next[i + 1] = true,
+                    _ => {}
+                }
+            }
+            self.skip_empty(&mut next);
+            if !next.contains(&true) {
+                return false;
+            }
+            states = next;
+        }
+        states[self.pieces.len()]
+    }
+
+    // Las secuencias pueden estar vacías: se puede pasar a la pieza siguiente sin leer nada
+    fn skip_empty(&self, states: &mut [bool]) {
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if states[i] && matches!(piece, Piece::Many { .. }) {
+                states[i + 1] = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_captures() {
+        let regex = CompiledRegex::try_new(r"(\w+)@(corp)?(\.com)").unwrap();
+        assert!(regex.is_match("ana@corp.com"));
+        assert_eq!(
+            regex.captures("ana@.com"),
+            Some(vec![Some("ana@.com".to_string()), Some("ana".to_string()), None, Some(".com".to_string())])
+        );
+        assert_eq!(regex.captures("sin arroba"), None);
+    }
+
+    #[test]
+    fn test_like() {
+        assert!(Wildcard::like("Jo%n_", false).is_match("Johnny"));
+        assert!(!Wildcard::like("Jo%n_", false).is_match("Jon"));
+        assert!(!Wildcard::like("jo%", false).is_match("John"));
+        assert!(Wildcard::like("jo%", true).is_match("JOHN"));
+        assert!(Wildcard::like("ÁRBOL_", true).is_match("árbole"));
+        assert!(Wildcard::like(r"100\%", false).is_match("100%"));
+        assert!(!Wildcard::like(r"100\%", false).is_match("1000"));
+        // Muchos comodines no provocan una explosión de vuelta atrás
+        let pattern = "%a".repeat(30) + "b";
+        assert!(!Wildcard::like(&pattern, false).is_match(&"a".repeat(5000)));
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(Wildcard::glob("/var/log/*.log").unwrap().is_match("/var/log/syslog.log"));
+        assert!(!Wildcard::glob("/var/log/*.log").unwrap().is_match("/var/log/nginx/access.log"));
+        assert!(Wildcard::glob("/var/**.log").unwrap().is_match("/var/log/nginx/access.log"));
+        assert!(Wildcard::glob("file?.[ct]xt").unwrap().is_match("file1.txt"));
+        assert!(!Wildcard::glob("file?.[!ct]xt").unwrap().is_match("file1.txt"));
+        assert!(Wildcard::glob("[a-c]-[0-9]").unwrap().is_match("b-7"));
+        assert!(Wildcard::glob("[a-]").unwrap().is_match("-"));
+    }
+
+    #[test]
+    fn test_glob_unclosed_class() {
+        assert_eq!(Wildcard::glob("[abc"), Err("Falta ']' en el patrón glob \"[abc\"".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(CompiledRegex::try_new("(abc").unwrap_err().starts_with("Expresión regular no válida \"(abc\""));
+    }
+}
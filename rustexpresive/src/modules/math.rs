@@ -0,0 +1,250 @@
+#[cfg(feature = "bignum")]
+use crate::modules::bignum;
+use crate::modules::error::{fail, ExprError};
+use crate::modules::functions::Builtin;
+use crate::modules::functions::ValueType::{Float, Number};
+use crate::modules::interprete::{compare_numbers, finite, power, Context, EvalResult};
+#[cfg(feature = "bignum")]
+use num_traits::Signed;
+use std::cmp::Ordering;
+
+// Funciones matemáticas (característica "math", activa por defecto). Las que no cambian el tipo
+// (abs, min, max, floor, ceil, clamp) devuelven un entero o un decimal exacto si lo reciben; las
+// demás trabajan con f64 y fallan si el resultado no es finito (sqrt(-1), log(0)).
+pub(crate) const BUILTINS: &[Builtin] = &[
+    Builtin { name: "abs", params: &[Number], required: 1, returns: Number, call: abs },
+    Builtin { name: "min", params: &[Number, Number], required: 2, returns: Number, call: min },
+    Builtin { name: "max", params: &[Number, Number], required: 2, returns: Number, call: max },
+    Builtin { name: "clamp", params: &[Number, Number, Number], required: 3, returns: Number, call: clamp },
+    Builtin { name: "floor", params: &[Number], required: 1, returns: Number, call: floor },
+    Builtin { name: "ceil", params: &[Number], required: 1, returns: Number, call: ceil },
+    Builtin { name: "pow", params: &[Number, Number], required: 2, returns: Number, call: pow },
+    Builtin { name: "sqrt", params: &[Number], required: 1, returns: Float, call: sqrt },
+    Builtin { name: "exp", params: &[Number], required: 1, returns: Float, call: exp },
+    Builtin { name: "log", params: &[Number, Number], required: 1, returns: Float, call: log },
+    // Trigonometría, en radianes
+    Builtin { name: "sin", params: &[Number], required: 1, returns: Float, call: sin },
+    Builtin { name: "cos", params: &[Number], required: 1, returns: Float, call: cos },
+    Builtin { name: "tan", params: &[Number], required: 1, returns: Float, call: tan },
+    Builtin { name: "asin", params: &[Number], required: 1, returns: Float, call: asin },
+    Builtin { name: "acos", params: &[Number], required: 1, returns: Float, call: acos },
+    Builtin { name: "atan", params: &[Number], required: 1, returns: Float, call: atan },
+    Builtin { name: "atan2", params: &[Number, Number], required: 2, returns: Float, call: atan2 },
+];
+
+// Constantes que se pueden usar como variables: PI * r ** 2
+pub(crate) fn constant(name: &str) -> Option<EvalResult> {
+    match name {
+        "PI" => Some(EvalResult::Float(std::f64::consts::PI)),
+        "E" => Some(EvalResult::Float(std::f64::consts::E)),
+        _ => None,
+    }
+}
+
+fn abs(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    match &args[0] {
+        EvalResult::Int(n) => match n.checked_abs() {
+            Some(n) => Ok(EvalResult::Int(n)),
+            #[cfg(feature = "bignum")]
+            None => Ok(bignum::normalize(bignum::from_int(*n).abs())),
+            #[cfg(not(feature = "bignum"))]
+            None => Err(ExprError::Overflow(format!("abs({})", n))),
+        },
+        EvalResult::Float(n) => Ok(EvalResult::Float(n.abs())),
+        EvalResult::Decimal(n) => Ok(EvalResult::Decimal(n.abs())),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(n) => Ok(bignum::normalize(n.abs())),
+        _ => unreachable!("la firma garantiza un número"),
+    }
+}
+
+fn min(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    pick(args, Ordering::Less)
+}
+
+fn max(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    pick(args, Ordering::Greater)
+}
+
+// El menor o el mayor de dos números, sin convertirlo: min(2, 3.5) es el entero 2
+fn pick(mut args: Vec<EvalResult>, wanted: Ordering) -> Result<EvalResult, ExprError> {
+    let right = args.pop().unwrap();
+    let left = args.pop().unwrap();
+    match ordering(&left, &right)? {
+        Ordering::Equal => Ok(left),
+        order if order == wanted => Ok(left),
+        _ => Ok(right),
+    }
+}
+
+// clamp(x, mínimo, máximo): x limitado al intervalo
+fn clamp(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let [value, low, high]: [EvalResult; 3] = args.try_into().unwrap();
+    if ordering(&low, &high)? == Ordering::Greater {
+        fail!("clamp espera un mínimo ({}) menor o igual que el máximo ({})", low, high);
+    }
+    if ordering(&value, &low)? == Ordering::Less {
+        return Ok(low);
+    }
+    if ordering(&value, &high)? == Ordering::Greater {
+        return Ok(high);
+    }
+    Ok(value)
+}
+
+fn ordering(left: &EvalResult, right: &EvalResult) -> Result<Ordering, ExprError> {
+    match compare_numbers(left, right) {
+        Some(order) => Ok(order),
+        None => fail!("No se pueden comparar {} y {}", left, right),
+    }
+}
+
+fn floor(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    match &args[0] {
+        EvalResult::Int(n) => Ok(EvalResult::Int(*n)),
+        EvalResult::Float(n) => Ok(EvalResult::Float(n.floor())),
+        EvalResult::Decimal(n) => Ok(EvalResult::Decimal(n.floor())),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(n) => Ok(bignum::normalize(n.floor())),
+        _ => unreachable!("la firma garantiza un número"),
+    }
+}
+
+fn ceil(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    match &args[0] {
+        EvalResult::Int(n) => Ok(EvalResult::Int(*n)),
+        EvalResult::Float(n) => Ok(EvalResult::Float(n.ceil())),
+        EvalResult::Decimal(n) => Ok(EvalResult::Decimal(n.ceil())),
+        #[cfg(feature = "bignum")]
+        EvalResult::Rational(n) => Ok(bignum::normalize(n.ceil())),
+        _ => unreachable!("la firma garantiza un número"),
+    }
+}
+
+// pow(base, exponente) se comporta igual que el operador **
+fn pow(mut args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let exponent = args.pop().unwrap();
+    let base = args.pop().unwrap();
+    power(base, exponent)
+}
+
+// log(x) es el logaritmo natural; log(x, base) el de cualquier otra base
+fn log(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let value = float_arg(&args[0]);
+    match args.get(1) {
+        None => finite(value.ln(), || format!("log({})", args[0])),
+        Some(base) => {
+            // Las bases 10 y 2 usan su función propia: ln(1000) / ln(10) no da exactamente 3
+            let result = match float_arg(base) {
+                10.0 => value.log10(),
+                2.0 => value.log2(),
+                base => value.ln() / base.ln(),
+            };
+            finite(result, || format!("log({}, {})", args[0], base))
+        }
+    }
+}
+
+fn atan2(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let (y, x) = (float_arg(&args[0]), float_arg(&args[1]));
+    finite(y.atan2(x), || format!("atan2({}, {})", args[0], args[1]))
+}
+
+// Funciones de una variable real
+fn unary(name: &str, args: &[EvalResult], function: fn(f64) -> f64) -> Result<EvalResult, ExprError> {
+    finite(function(float_arg(&args[0])), || format!("{}({})", name, args[0]))
+}
+
+fn sqrt(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("sqrt", &args, f64::sqrt)
+}
+
+fn exp(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("exp", &args, f64::exp)
+}
+
+fn sin(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("sin", &args, f64::sin)
+}
+
+fn cos(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("cos", &args, f64::cos)
+}
+
+fn tan(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("tan", &args, f64::tan)
+}
+
+fn asin(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("asin", &args, f64::asin)
+}
+
+fn acos(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("acos", &args, f64::acos)
+}
+
+fn atan(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    unary("atan", &args, f64::atan)
+}
+
+fn float_arg(value: &EvalResult) -> f64 {
+    value.as_float().expect("la firma garantiza un número")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::functions::{call_builtin, signature};
+    use crate::modules::interprete::{Context, EvalResult};
+    use crate::modules::error::ExprError;
+    use rust_decimal::Decimal;
+
+    fn call(name: &str, args: Vec<EvalResult>) -> Result<EvalResult, ExprError> {
+        call_builtin(name, args, &mut Context::new()).unwrap()
+    }
+
+    #[test]
+    fn test_keep_numeric_type() {
+        let price = EvalResult::Decimal(Decimal::new(-1999, 2));
+        assert_eq!(call("abs", vec![price.clone()]), Ok(EvalResult::Decimal(Decimal::new(1999, 2))));
+        assert_eq!(call("floor", vec![price.clone()]), Ok(EvalResult::Decimal(Decimal::new(-20, 0))));
+        assert_eq!(call("ceil", vec![EvalResult::Float(1.2)]), Ok(EvalResult::Float(2.0)));
+        assert_eq!(call("floor", vec![EvalResult::Int(7)]), Ok(EvalResult::Int(7)));
+        assert_eq!(call("min", vec![EvalResult::Int(2), EvalResult::Float(3.5)]), Ok(EvalResult::Int(2)));
+        assert_eq!(call("max", vec![price, EvalResult::Int(0)]), Ok(EvalResult::Int(0)));
+        assert_eq!(signature("clamp").unwrap(), "clamp(number, number, number) -> number");
+        assert_eq!(signature("log").unwrap(), "log(number, number?) -> float");
+    }
+
+    #[test]
+    fn test_clamp() {
+        let clamp = |value: i64| call("clamp", vec![EvalResult::Int(value), EvalResult::Int(0), EvalResult::Int(10)]);
+        assert_eq!(clamp(-5), Ok(EvalResult::Int(0)));
+        assert_eq!(clamp(5), Ok(EvalResult::Int(5)));
+        assert_eq!(clamp(50), Ok(EvalResult::Int(10)));
+        assert_eq!(
+            call("clamp", vec![EvalResult::Int(1), EvalResult::Int(10), EvalResult::Int(0)]),
+            Err(ExprError::Runtime("clamp espera un mínimo (10) menor o igual que el máximo (0)".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_real_functions() {
+        assert_eq!(call("sqrt", vec![EvalResult::Int(16)]), Ok(EvalResult::Float(4.0)));
+        assert_eq!(call("log", vec![EvalResult::Int(1000), EvalResult::Int(10)]), Ok(EvalResult::Float(3.0)));
+        assert_eq!(call("log", vec![EvalResult::Int(1024), EvalResult::Int(2)]), Ok(EvalResult::Float(10.0)));
+        assert_eq!(call("exp", vec![EvalResult::Int(0)]), Ok(EvalResult::Float(1.0)));
+        assert_eq!(call("atan2", vec![EvalResult::Int(1), EvalResult::Int(1)]), Ok(EvalResult::Float(std::f64::consts::FRAC_PI_4)));
+        assert_eq!(call("sqrt", vec![EvalResult::Int(-1)]), Err(ExprError::NotFinite("sqrt(-1)".to_string())));
+        assert_eq!(call("log", vec![EvalResult::Int(0)]), Err(ExprError::NotFinite("log(0)".to_string())));
+        assert_eq!(call("sin", vec![EvalResult::Null]), Ok(EvalResult::Null));
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    fn test_abs_overflow() {
+        assert_eq!(
+            call("abs", vec![EvalResult::Int(i64::MIN)]),
+            Err(ExprError::Overflow(format!("abs({})", i64::MIN)))
+        );
+    }
+}
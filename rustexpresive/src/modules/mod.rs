@@ -3,4 +3,27 @@ pub mod tokenizer;
 
 pub mod parser;
 
-pub mod interprete;
\ No newline at end of file
+pub mod interprete;
+
+pub mod loader;
+
+pub mod functions;
+pub mod template;
+
+pub mod matching;
+
+pub mod statistics;
+
+pub mod finance;
+
+pub mod collation;
+
+pub mod error;
+
+pub mod decimal;
+
+#[cfg(feature = "bignum")]
+pub mod bignum;
+
+#[cfg(feature = "math")]
+pub mod math;
@@ -1,6 +1,19 @@
-use crate::modules::tokenizer::Token;
+use crate::modules::error::ExprError;
+use crate::modules::matching::CompiledRegex;
+use crate::modules::tokenizer::{StringPart, Token};
+#[cfg(feature = "bignum")]
+use num_rational::BigRational;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
 
-#[derive(Debug, PartialEq)]
+// Guarda un ExprError::Syntax con el mensaje indicado y termina el análisis (ver Parser::fail)
+macro_rules! parse_fail {
+    ($parser:expr, $($arg:tt)*) => {
+        return $parser.fail(format!($($arg)*))
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     BooleanLiteral(bool),  // True/False
     StringLiteral(String), // "Hola mundo"
@@ -9,21 +22,307 @@ pub enum Expr {
     Or(Box<Expr>, Box<Expr>),   // ||
     Equal(Box<Expr>, Box<Expr>), //   ==
     NotEqual(Box<Expr>, Box<Expr>), //  !=
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>), // cond ? a : b, if c then a else b
+    Case(Vec<(Expr, Expr)>, Option<Box<Expr>>), // case when c then a ... else b end
+    NumberLiteral(f64), // 3.14
+    IntegerLiteral(i64), // 42
+    DecimalLiteral(Decimal), // 19.99d
+    #[cfg(feature = "bignum")]
+    RationalLiteral(BigRational), // 0.1 con la característica bignum
+    Variable(String),   // total
+    Negate(Box<Expr>),  // -x
+    Less(Box<Expr>, Box<Expr>),         // <
+    LessEqual(Box<Expr>, Box<Expr>),    // <=
+    Greater(Box<Expr>, Box<Expr>),      // >
+    GreaterEqual(Box<Expr>, Box<Expr>), // >=
+    Match(Box<Expr>, Vec<(Vec<Pattern>, Expr)>), // match x { "a" | "b" => 1, _ => 0 }
+    Add(Box<Expr>, Box<Expr>),      // +
+    Subtract(Box<Expr>, Box<Expr>), // -
+    Multiply(Box<Expr>, Box<Expr>), // *
+    Divide(Box<Expr>, Box<Expr>),   // /
+    Let(String, Box<Expr>, Box<Expr>), // let x = valor in cuerpo
+    Assign(String, Box<Expr>), // x = valor; (solo como sentencia de un programa)
+    FunctionDef(String, Vec<String>, Box<Expr>), // fn nombre(a, b) = cuerpo; (solo como sentencia)
+    Call(String, Vec<Expr>), // nombre(1, x), geo::distance(a, b)
+    Import(String, Option<String>), // import "ruta" as nombre; (solo como sentencia)
+    Lambda(Vec<String>, Box<Expr>), // x -> x * 2, (a, b) -> a + b
+    ListLiteral(Vec<Expr>),         // [1, 2, 3]
+    Index(Box<Expr>, Box<Expr>),    // lista[0]
+    In(Box<Expr>, Box<Expr>),       // x in lista
+    NotIn(Box<Expr>, Box<Expr>),    // x not in lista
+    Member(Box<Expr>, String),      // user.address
+    NullLiteral,                    // null
+    IsNull(Box<Expr>),              // x is null
+    IsNotNull(Box<Expr>),           // x is not null
+    Coalesce(Box<Expr>, Box<Expr>), // x ?? valor_por_defecto
+    SafeMember(Box<Expr>, String),  // user?.address
+    Interpolate(Vec<Expr>),         // "Hola ${user.name}"
+    RegexMatch(Box<Expr>, Box<Expr>), // email =~ r"@corp\.com$"
+    Captures(Box<Expr>, Box<Expr>),   // captures(s, r"(\d+)-(\d+)") con un patrón literal
+    Regex(CompiledRegex),           // patrón literal ya compilado
+    Like(Box<Expr>, Box<Expr>),     // name like "Jo%n_"
+    ILike(Box<Expr>, Box<Expr>),    // name ilike "jo%"
+    Glob(Box<Expr>, Box<Expr>),     // path glob "/var/log/*.log"
+    CaselessEqual(Box<Expr>, Box<Expr>), // pais ~= "ESPAÑA"
+    Modulo(Box<Expr>, Box<Expr>),    // %
+    IntDivide(Box<Expr>, Box<Expr>), // //
+    Power(Box<Expr>, Box<Expr>),     // **
+    BitAnd(Box<Expr>, Box<Expr>),     // &
+    BitOr(Box<Expr>, Box<Expr>),      // |
+    BitXor(Box<Expr>, Box<Expr>),     // ^
+    ShiftLeft(Box<Expr>, Box<Expr>),  // <<
+    ShiftRight(Box<Expr>, Box<Expr>), // >>
+    BitNot(Box<Expr>),                // ~
+}
+
+// Patrones de los brazos de un match
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Literal(Expr), // "active", 1, true
+    Wildcard,      // _
+}
+
+// Información sobre las variables conocida en tiempo de análisis
+#[derive(Debug, Default)]
+pub struct Schema {
+    enums: HashMap<String, Vec<String>>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    // Declara los valores posibles (cadenas) de una variable
+    pub fn add_enum(&mut self, name: &str, values: &[&str]) {
+        self.enums.insert(name.to_string(), values.iter().map(|v| v.to_string()).collect());
+    }
+
+    pub fn enum_values(&self, name: &str) -> Option<&Vec<String>> {
+        self.enums.get(name)
+    }
 }
 
 pub struct Parser<'a> {
     tokens: &'a [Token],
     current: usize,
+    schema: Option<&'a Schema>,
+    // false mientras se analiza el valor de un let, donde "in" cierra el enlace
+    allow_in: bool,
+    // Primer error de sintaxis; al encontrarlo, todas las funciones de análisis devuelven None
+    error: Option<ExprError>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token]) -> Self {
-        Parser { tokens, current: 0 }
+        Parser { tokens, current: 0, schema: None, allow_in: true, error: None }
+    }
+
+    // Parser que usa el esquema para validar, por ejemplo, que los match sean exhaustivos
+    pub fn with_schema(tokens: &'a [Token], schema: &'a Schema) -> Self {
+        Parser { tokens, current: 0, schema: Some(schema), allow_in: true, error: None }
     }
 
-    // Función publica para analizar la expresión
+    // Función publica para analizar la expresión; no admite tokens sobrantes. Un error de sintaxis
+    // hace que se detenga con un panic (usa try_parse para recibirlo como un ExprError)
     pub fn parse(&mut self) -> Option<Expr> {
-        self.parse_or()
+        let expr = self.parse_single();
+        self.raise();
+        expr
+    }
+
+    pub fn try_parse(&mut self) -> Result<Expr, ExprError> {
+        let expr = self.parse_single();
+        self.finish(expr)
+    }
+
+    // Función publica para analizar un programa: sentencias separadas por ';'. Como parse, un error
+    // de sintaxis es un panic (usa try_parse_program para recibirlo como un ExprError)
+    pub fn parse_program(&mut self) -> Option<Vec<Expr>> {
+        let statements = self.parse_statements();
+        self.raise();
+        statements.filter(|statements| !statements.is_empty())
+    }
+
+    // Un programa sin sentencias es válido y devuelve una lista vacía
+    pub fn try_parse_program(&mut self) -> Result<Vec<Expr>, ExprError> {
+        let statements = self.parse_statements();
+        self.finish(statements)
+    }
+
+    fn parse_single(&mut self) -> Option<Expr> {
+        let expr = self.parse_expression()?;
+        if let Some(token) = self.peek() {
+            parse_fail!(self, "Token inesperado después de la expresión: {:?}", token);
+        }
+        Some(expr)
+    }
+
+    fn parse_statements(&mut self) -> Option<Vec<Expr>> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            statements.push(self.parse_statement()?);
+            match self.peek() {
+                Some(Token::Semicolon) => {
+                    self.advance(); // Consumiendo ";"
+                }
+                Some(token) => parse_fail!(self, "Se esperaba ';' entre sentencias y se encontró {:?}", token),
+                None => break,
+            }
+        }
+        Some(statements)
+    }
+
+    // Guarda el primer error de sintaxis; el None que devuelve corta el análisis con "?"
+    fn fail<T>(&mut self, message: String) -> Option<T> {
+        self.error.get_or_insert(ExprError::Syntax(message));
+        None
+    }
+
+    // Lanza como pánico el error guardado, para las funciones que no devuelven Result
+    fn raise(&mut self) {
+        if let Some(err) = self.error.take() {
+            panic!("{}", err);
+        }
+    }
+
+    // El error guardado o, si el análisis terminó sin un valor ni un error, una expresión incompleta
+    fn finish<T>(&mut self, parsed: Option<T>) -> Result<T, ExprError> {
+        match (self.error.take(), parsed) {
+            (Some(err), _) => Err(err),
+            (None, Some(parsed)) => Ok(parsed),
+            (None, None) => Err(ExprError::Syntax("Not valid expresion".to_string())),
+        }
+    }
+
+    // Parse para una sentencia: importación, definición de función, asignación (x = valor) o expresión
+    fn parse_statement(&mut self) -> Option<Expr> {
+        if let Some(Token::Fn) = self.peek() {
+            return self.parse_function_def();
+        }
+        if let Some(Token::Import) = self.peek() {
+            return self.parse_import();
+        }
+        if let (Some(Token::Identifier(name)), Some(Token::Assign)) =
+            (self.peek(), self.tokens.get(self.current + 1))
+        {
+            let name = name.to_string();
+            self.advance(); // Consumiendo el nombre
+            self.advance(); // Consumiendo "="
+            let value = self.parse_expression()?;
+            return Some(Expr::Assign(name, Box::new(value)));
+        }
+        self.parse_expression()
+    }
+
+    // Los patrones literales de =~ se compilan al analizar (un patrón no válido es un error de
+    // sintaxis); el resto se compila al evaluar
+    fn compile_regex(&mut self, pattern: Expr) -> Option<Expr> {
+        match pattern {
+            Expr::StringLiteral(pattern) => match CompiledRegex::try_new(&pattern) {
+                Ok(regex) => Some(Expr::Regex(regex)),
+                Err(err) => parse_fail!(self, "{}", err),
+            },
+            other => Some(other),
+        }
+    }
+
+    // matches y captures con un patrón literal lo compilan al analizar. Siguen siendo llamadas: al
+    // evaluar, una función del usuario con el mismo nombre tiene prioridad sobre la integrada.
+    fn regex_call(&mut self, name: String, mut args: Vec<Expr>) -> Option<Expr> {
+        if let ("matches" | "captures", [_, Expr::StringLiteral(_)]) = (name.as_str(), args.as_slice()) {
+            let pattern = args.pop().unwrap();
+            args.push(self.compile_regex(pattern)?);
+        }
+        Some(Expr::Call(name, args))
+    }
+
+    // Parse para import "ruta" [as nombre]
+    fn parse_import(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "import"
+        let path = match self.advance() {
+            Some(Token::StringLiteral(path)) => path.to_string(),
+            _ => parse_fail!(self, "Se esperaba la ruta del módulo entre comillas después de 'import'"),
+        };
+        let alias = if let Some(Token::As) = self.peek() {
+            self.advance(); // Consumiendo "as"
+            match self.advance() {
+                Some(Token::Identifier(alias)) => Some(alias.to_string()),
+                _ => parse_fail!(self, "Se esperaba un nombre después de 'as'"),
+            }
+        } else {
+            None
+        };
+        Some(Expr::Import(path, alias))
+    }
+
+    // Parse para fn nombre(a, b) = cuerpo
+    fn parse_function_def(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "fn"
+        let name = match self.advance() {
+            Some(Token::Identifier(name)) => name.to_string(),
+            _ => parse_fail!(self, "Se esperaba el nombre de la función después de 'fn'"),
+        };
+        self.expect(Token::LParen, "Se esperaba '(' después del nombre de la función")?;
+        let mut params = Vec::new();
+        while self.peek() != Some(&Token::RParen) {
+            match self.advance() {
+                Some(Token::Identifier(param)) => params.push(param.to_string()),
+                _ => parse_fail!(self, "Se esperaba el nombre de un parámetro en la función {}", name),
+            }
+            if let Some(Token::Comma) = self.peek() {
+                self.advance(); // Consumiendo ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RParen, "Se esperaba ')' al final de los parámetros")?;
+        self.expect(Token::Assign, "Se esperaba '=' antes del cuerpo de la función")?;
+        let body = self.parse_expression()?;
+        Some(Expr::FunctionDef(name, params, Box::new(body)))
+    }
+
+    // Parse para los argumentos de una llamada: (a, b, ...)
+    fn parse_arguments(&mut self) -> Option<Vec<Expr>> {
+        self.advance(); // Consumiendo "("
+        let mut args = Vec::new();
+        while self.peek() != Some(&Token::RParen) {
+            args.push(self.parse_nested()?);
+            if let Some(Token::Comma) = self.peek() {
+                self.advance(); // Consumiendo ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RParen, "Se esperaba ')' al final de los argumentos")?;
+        Some(args)
+    }
+
+    // Si en la posición actual empieza una lista de parámetros "(a, b) ->", la consume y la devuelve
+    fn parse_lambda_params(&mut self) -> Option<Vec<String>> {
+        let mut position = self.current + 1; // Saltando "("
+        let mut params = Vec::new();
+        while let Some(Token::Identifier(param)) = self.tokens.get(position) {
+            params.push(param.to_string());
+            position += 1;
+            if let Some(Token::Comma) = self.tokens.get(position) {
+                position += 1;
+            } else {
+                break;
+            }
+        }
+        match (self.tokens.get(position), self.tokens.get(position + 1)) {
+            (Some(Token::RParen), Some(Token::Arrow)) => {
+                self.current = position + 2; // Consumiendo ") ->"
+                Some(params)
+            }
+            _ => None,
+        }
+    }
+
+    // Parse para una expresión completa, de menor a mayor precedencia
+    fn parse_expression(&mut self) -> Option<Expr> {
+        self.parse_conditional()
     }
 
     // Función para avanzar al siguiente token
@@ -37,6 +336,29 @@ impl<'a> Parser<'a> {
         self.tokens.get(self.current)
     }
 
+    // Parse para el operador ternario (cond ? a : b), asociativo por la derecha
+    fn parse_conditional(&mut self) -> Option<Expr> {
+        let condition = self.parse_or()?;
+
+        if let Some(Token::Question) = self.peek() {
+            self.advance(); // Consumiendo el token "?"
+            let then_branch = self.parse_conditional()?;
+            if let Some(Token::Colon) = self.peek() {
+                self.advance(); // Consumiendo el token ":"
+            } else {
+                parse_fail!(self, "Se esperaba ':' en el operador ternario");
+            }
+            let else_branch = self.parse_conditional()?;
+            return Some(Expr::Conditional(
+                Box::new(condition),
+                Box::new(then_branch),
+                Box::new(else_branch),
+            ));
+        }
+
+        Some(condition)
+    }
+
     // Parse para OR
     fn parse_or(&mut self) -> Option<Expr> {
         let mut left = self.parse_and()?;
@@ -65,35 +387,478 @@ impl<'a> Parser<'a> {
 
     // Parse para las expresiones de comparación (== y !=)
     fn parse_equal(&mut self) -> Option<Expr> {
-        let mut left = self.parse_not()?;
+        let mut left = self.parse_comparison()?;
     
         while let Some(token) = self.peek() {
             match token {
                 Token::Equal => {
                     self.advance(); // Consumiendo el token "=="
-                    let right = self.parse_not()?;
+                    let right = self.parse_comparison()?;
                     left = Expr::Equal(Box::new(left), Box::new(right));
                 }
                 Token::NotEqual => {
                     self.advance(); // Consumiendo el token "!="
-                    let right = self.parse_not()?;
+                    let right = self.parse_comparison()?;
                     left = Expr::NotEqual(Box::new(left), Box::new(right));
                 }
+                Token::CaselessEqual => {
+                    self.advance(); // Consumiendo el token "~="
+                    let right = self.parse_comparison()?;
+                    left = Expr::CaselessEqual(Box::new(left), Box::new(right));
+                }
                 _ => break,
             }
         }
         Some(left)
     }
+
+    // Parse para las comparaciones de orden (<, <=, > y >=), de pertenencia (in y not in)
+    // y de nulos (is null, is not null)
+    fn parse_comparison(&mut self) -> Option<Expr> {
+        let mut left = self.parse_coalesce()?;
+
+        while let Some(token) = self.peek() {
+            if let Token::Is = token {
+                self.advance(); // Consumiendo "is"
+                let negated = self.peek() == Some(&Token::NotKeyword);
+                if negated {
+                    self.advance(); // Consumiendo "not"
+                }
+                self.expect(Token::Null, "Se esperaba 'null' después de 'is'")?;
+                left = if negated {
+                    Expr::IsNotNull(Box::new(left))
+                } else {
+                    Expr::IsNull(Box::new(left))
+                };
+                continue;
+            }
+            let regex = token == &Token::RegexMatch;
+            let build: fn(Box<Expr>, Box<Expr>) -> Expr = match token {
+                Token::Less => Expr::Less,
+                Token::LessEqual => Expr::LessEqual,
+                Token::Greater => Expr::Greater,
+                Token::GreaterEqual => Expr::GreaterEqual,
+                Token::RegexMatch => Expr::RegexMatch,
+                Token::In if self.allow_in => Expr::In,
+                Token::NotKeyword if self.allow_in && self.tokens.get(self.current + 1) == Some(&Token::In) => {
+                    self.advance(); // Consumiendo "not"
+                    Expr::NotIn
+                }
+                Token::Like => Expr::Like,
+                Token::ILike => Expr::ILike,
+                Token::Glob => Expr::Glob,
+                // x not like p equivale a !(x like p)
+                Token::NotKeyword => {
+                    let build: fn(Box<Expr>, Box<Expr>) -> Expr = match self.tokens.get(self.current + 1) {
+                        Some(Token::Like) => |left, right| Expr::Not(Box::new(Expr::Like(left, right))),
+                        Some(Token::ILike) => |left, right| Expr::Not(Box::new(Expr::ILike(left, right))),
+                        Some(Token::Glob) => |left, right| Expr::Not(Box::new(Expr::Glob(left, right))),
+                        _ => break,
+                    };
+                    self.advance(); // Consumiendo "not"
+                    build
+                }
+                _ => break,
+            };
+            self.advance(); // Consumiendo el operador
+            let mut right = self.parse_coalesce()?;
+            if regex {
+                right = self.compile_regex(right)?;
+            }
+            left = build(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    // Parse para x ?? valor_por_defecto, asociativo por la derecha y más fuerte que las comparaciones
+    fn parse_coalesce(&mut self) -> Option<Expr> {
+        let left = self.parse_bit_or()?;
+
+        if let Some(Token::Coalesce) = self.peek() {
+            self.advance(); // Consumiendo "??"
+            let right = self.parse_coalesce()?;
+            return Some(Expr::Coalesce(Box::new(left), Box::new(right)));
+        }
+        Some(left)
+    }
+
+    // Los operadores de bits siguen la precedencia de Python: | < ^ < & < desplazamientos < suma,
+    // todos más fuertes que las comparaciones (flags & MASK == 0 compara el resultado de &)
+    fn parse_bit_or(&mut self) -> Option<Expr> {
+        let mut left = self.parse_bit_xor()?;
+
+        while let Some(Token::Pipe) = self.peek() {
+            self.advance(); // Consumiendo "|"
+            let right = self.parse_bit_xor()?;
+            left = Expr::BitOr(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn parse_bit_xor(&mut self) -> Option<Expr> {
+        let mut left = self.parse_bit_and()?;
+
+        while let Some(Token::BitXor) = self.peek() {
+            self.advance(); // Consumiendo "^"
+            let right = self.parse_bit_and()?;
+            left = Expr::BitXor(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn parse_bit_and(&mut self) -> Option<Expr> {
+        let mut left = self.parse_shift()?;
+
+        while let Some(Token::BitAnd) = self.peek() {
+            self.advance(); // Consumiendo "&"
+            let right = self.parse_shift()?;
+            left = Expr::BitAnd(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn parse_shift(&mut self) -> Option<Expr> {
+        let mut left = self.parse_additive()?;
+
+        while let Some(token) = self.peek() {
+            let build: fn(Box<Expr>, Box<Expr>) -> Expr = match token {
+                Token::ShiftLeft => Expr::ShiftLeft,
+                Token::ShiftRight => Expr::ShiftRight,
+                _ => break,
+            };
+            self.advance(); // Consumiendo el operador
+            let right = self.parse_additive()?;
+            left = build(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    // Parse para la suma y la resta
+    fn parse_additive(&mut self) -> Option<Expr> {
+        let mut left = self.parse_multiplicative()?;
+
+        while let Some(token) = self.peek() {
+            let build: fn(Box<Expr>, Box<Expr>) -> Expr = match token {
+                Token::Plus => Expr::Add,
+                Token::Minus => Expr::Subtract,
+                _ => break,
+            };
+            self.advance(); // Consumiendo el operador
+            let right = self.parse_multiplicative()?;
+            left = build(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    // Parse para la multiplicación, la división, la división entera y el resto
+    fn parse_multiplicative(&mut self) -> Option<Expr> {
+        let mut left = self.parse_not()?;
+
+        while let Some(token) = self.peek() {
+            let build: fn(Box<Expr>, Box<Expr>) -> Expr = match token {
+                Token::Multiply => Expr::Multiply,
+                Token::Divide => Expr::Divide,
+                Token::IntDivide => Expr::IntDivide,
+                Token::Modulo => Expr::Modulo,
+                _ => break,
+            };
+            self.advance(); // Consumiendo el operador
+            let right = self.parse_not()?;
+            left = build(Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
     
-    // Parse para NOT
+    // Parse para NOT, el menos unario y el ~ de bits
     fn parse_not(&mut self) -> Option<Expr> {
         if let Some(Token::Not) = self.peek() {
             self.advance(); // Consumiendo el token "!"
-            let expr = self.parse_atom()?;
+            let expr = self.parse_power()?;
             return Some(Expr::Not(Box::new(expr)));
         }
 
-        self.parse_atom()
+        if let Some(Token::Minus) = self.peek() {
+            self.advance(); // Consumiendo el token "-"
+            let expr = self.parse_power()?;
+            return Some(Expr::Negate(Box::new(expr)));
+        }
+
+        if let Some(Token::BitNot) = self.peek() {
+            self.advance(); // Consumiendo el token "~"
+            let expr = self.parse_power()?;
+            return Some(Expr::BitNot(Box::new(expr)));
+        }
+
+        self.parse_power()
+    }
+
+    // Parse para la potencia: más fuerte que el menos unario de la izquierda (-2 ** 2 es -4)
+    // y asociativa por la derecha (2 ** 3 ** 2 es 2 ** 9)
+    fn parse_power(&mut self) -> Option<Expr> {
+        let base = self.parse_postfix()?;
+
+        if let Some(Token::Power) = self.peek() {
+            self.advance(); // Consumiendo "**"
+            let exponent = self.parse_not()?;
+            return Some(Expr::Power(Box::new(base), Box::new(exponent)));
+        }
+        Some(base)
+    }
+
+    // Parse para los accesos posteriores a un átomo: lista[0], mapa["clave"], user.address.city
+    fn parse_postfix(&mut self) -> Option<Expr> {
+        let mut expr = self.parse_atom()?;
+
+        loop {
+            match self.peek() {
+                Some(Token::LBracket) => {
+                    self.advance(); // Consumiendo "["
+                    let index = self.parse_nested()?;
+                    self.expect(Token::RBracket, "Se esperaba ']' al final del índice")?;
+                    expr = Expr::Index(Box::new(expr), Box::new(index));
+                }
+                Some(Token::Dot) => {
+                    self.advance(); // Consumiendo "."
+                    match self.advance() {
+                        Some(Token::Identifier(key)) => {
+                            expr = Expr::Member(Box::new(expr), key.to_string());
+                        }
+                        _ => parse_fail!(self, "Se esperaba un nombre después de '.'"),
+                    }
+                }
+                Some(Token::SafeDot) => {
+                    self.advance(); // Consumiendo "?."
+                    match self.advance() {
+                        Some(Token::Identifier(key)) => {
+                            expr = Expr::SafeMember(Box::new(expr), key.to_string());
+                        }
+                        _ => parse_fail!(self, "Se esperaba un nombre después de '?.'"),
+                    }
+                }
+                // "%" sin un operando detrás es un porcentaje (15%, x%); con él es el resto (7 % -2)
+                Some(Token::Modulo) if !starts_operand(self.tokens.get(self.current + 1)) => {
+                    self.advance(); // Consumiendo "%"
+                    expr = Expr::Divide(Box::new(expr), Box::new(Expr::IntegerLiteral(100)));
+                }
+                _ => break,
+            }
+        }
+        Some(expr)
+    }
+
+    // Parse para una expresión entre delimitadores, donde "in" vuelve a ser un operador
+    fn parse_nested(&mut self) -> Option<Expr> {
+        let allow_in = std::mem::replace(&mut self.allow_in, true);
+        let expr = self.parse_expression();
+        self.allow_in = allow_in;
+        expr
+    }
+
+    // Parse para los elementos de una lista literal: [a, b, ...]
+    fn parse_list(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "["
+        let mut items = Vec::new();
+        while self.peek() != Some(&Token::RBracket) {
+            items.push(self.parse_nested()?);
+            if let Some(Token::Comma) = self.peek() {
+                self.advance(); // Consumiendo ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RBracket, "Se esperaba ']' al final de la lista")?;
+        Some(Expr::ListLiteral(items))
+    }
+
+    // Consume el token esperado o falla con el mensaje indicado
+    fn expect(&mut self, expected: Token, message: &str) -> Option<()> {
+        if self.peek() != Some(&expected) {
+            parse_fail!(self, "{}", message);
+        }
+        self.advance();
+        Some(())
+    }
+
+    // Parse para if c1 then a else if c2 then b else d
+    fn parse_if(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "if"
+        let condition = self.parse_expression()?;
+        self.expect(Token::Then, "Se esperaba 'then' después de la condición del if")?;
+        let then_branch = self.parse_expression()?;
+        self.expect(Token::Else, "Se esperaba 'else' en la expresión if")?;
+        // El "else if" no necesita tratamiento especial: la rama else es otra expresión if
+        let else_branch = self.parse_expression()?;
+        Some(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        ))
+    }
+
+    // Parse para case when c1 then a when c2 then b else d end
+    fn parse_case(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "case"
+        let mut branches = Vec::new();
+        while let Some(Token::When) = self.peek() {
+            self.advance(); // Consumiendo "when"
+            let condition = self.parse_expression()?;
+            self.expect(Token::Then, "Se esperaba 'then' después de la condición del when")?;
+            let result = self.parse_expression()?;
+            branches.push((condition, result));
+        }
+        if branches.is_empty() {
+            parse_fail!(self, "Se esperaba al menos un 'when' en la expresión case");
+        }
+
+        let default = if let Some(Token::Else) = self.peek() {
+            self.advance(); // Consumiendo "else"
+            Some(Box::new(self.parse_expression()?))
+        } else {
+            None
+        };
+        self.expect(Token::End, "Se esperaba 'end' al final de la expresión case")?;
+        Some(Expr::Case(branches, default))
+    }
+
+    // Parse para let a = 1, b = a + 1 in cuerpo (varios enlaces se anidan de izquierda a derecha)
+    fn parse_let(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "let"
+        let mut bindings = Vec::new();
+        loop {
+            let name = match self.advance() {
+                Some(Token::Identifier(name)) => name.to_string(),
+                _ => parse_fail!(self, "Se esperaba un nombre después de 'let'"),
+            };
+            self.expect(Token::Assign, "Se esperaba '=' en el let")?;
+            let allow_in = std::mem::replace(&mut self.allow_in, false);
+            let value = self.parse_expression();
+            self.allow_in = allow_in;
+            bindings.push((name, value?));
+
+            if let Some(Token::Comma) = self.peek() {
+                self.advance(); // Consumiendo ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::In, "Se esperaba 'in' después de los valores del let")?;
+
+        let mut body = self.parse_expression()?;
+        for (name, value) in bindings.into_iter().rev() {
+            body = Expr::Let(name, Box::new(value), Box::new(body));
+        }
+        Some(body)
+    }
+
+    // Parse para match x { p1 | p2 => a, _ => b }
+    fn parse_match(&mut self) -> Option<Expr> {
+        self.advance(); // Consumiendo "match"
+        let subject = self.parse_expression()?;
+        self.expect(Token::LBrace, "Se esperaba '{' después del valor del match")?;
+
+        let mut arms = Vec::new();
+        while self.peek() != Some(&Token::RBrace) {
+            let mut patterns = vec![self.parse_pattern()?];
+            while let Some(Token::Pipe) = self.peek() {
+                self.advance(); // Consumiendo "|"
+                patterns.push(self.parse_pattern()?);
+            }
+            self.expect(Token::FatArrow, "Se esperaba '=>' después del patrón")?;
+            let result = self.parse_expression()?;
+            arms.push((patterns, result));
+
+            if let Some(Token::Comma) = self.peek() {
+                self.advance(); // Consumiendo ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RBrace, "Se esperaba '}' al final del match")?;
+
+        if arms.is_empty() {
+            parse_fail!(self, "El match debe tener al menos un brazo");
+        }
+        self.check_exhaustive(&subject, &arms)?;
+        Some(Expr::Match(Box::new(subject), arms))
+    }
+
+    // Parse para una cadena con interpolaciones: cada ${...} es una expresión completa
+    fn parse_interpolation(&mut self, parts: &[StringPart]) -> Option<Expr> {
+        let mut exprs = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Literal(text) => exprs.push(Expr::StringLiteral(text.to_string())),
+                StringPart::Code(tokens) => {
+                    let mut parser = Parser { tokens, current: 0, schema: self.schema, allow_in: true, error: None };
+                    match (parser.parse_single(), parser.error) {
+                        (_, Some(err)) => {
+                            self.error.get_or_insert(err);
+                            return None;
+                        }
+                        (Some(expr), None) => exprs.push(expr),
+                        (None, None) => parse_fail!(self, "Interpolación vacía en la cadena"),
+                    }
+                }
+            }
+        }
+        Some(Expr::Interpolate(exprs))
+    }
+
+    // Parse para un patrón literal o el comodín "_"
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        let literal = match self.advance() {
+            Some(Token::Identifier(name)) if name == "_" => return Some(Pattern::Wildcard),
+            Some(Token::StringLiteral(s)) => Expr::StringLiteral(s.to_string()),
+            Some(Token::Number(n)) => Expr::NumberLiteral(*n),
+            Some(Token::Integer(n)) => Expr::IntegerLiteral(*n),
+            Some(Token::Decimal(n)) => Expr::DecimalLiteral(*n),
+            #[cfg(feature = "bignum")]
+            Some(Token::Rational(n)) => Expr::RationalLiteral(n.clone()),
+            Some(Token::True) => Expr::BooleanLiteral(true),
+            Some(Token::False) => Expr::BooleanLiteral(false),
+            Some(Token::Null) => Expr::NullLiteral,
+            Some(Token::Minus) => match self.advance() {
+                Some(Token::Number(n)) => Expr::NumberLiteral(-*n),
+                Some(Token::Integer(n)) => Expr::IntegerLiteral(-*n),
+                Some(Token::Decimal(n)) => Expr::DecimalLiteral(-*n),
+                #[cfg(feature = "bignum")]
+                Some(Token::Rational(n)) => Expr::RationalLiteral(-n),
+                _ => parse_fail!(self, "Patrón no válido en match"),
+            },
+            _ => parse_fail!(self, "Patrón no válido en match"),
+        };
+        Some(Pattern::Literal(literal))
+    }
+
+    // Si no hay "_" y el esquema conoce los valores posibles de la variable, todos deben estar cubiertos
+    fn check_exhaustive(&mut self, subject: &Expr, arms: &[(Vec<Pattern>, Expr)]) -> Option<()> {
+        let patterns: Vec<&Pattern> = arms.iter().flat_map(|(patterns, _)| patterns).collect();
+        if patterns.contains(&&Pattern::Wildcard) {
+            return Some(());
+        }
+        let (Expr::Variable(name), Some(schema)) = (subject, self.schema) else {
+            return Some(());
+        };
+        let Some(values) = schema.enum_values(name) else {
+            return Some(());
+        };
+
+        for pattern in &patterns {
+            if let Pattern::Literal(Expr::StringLiteral(s)) = pattern {
+                if !values.contains(s) {
+                    parse_fail!(self, "\"{}\" no es un valor posible de {}", s, name);
+                }
+            }
+        }
+        let missing: Vec<String> = values
+            .iter()
+            .filter(|v| !patterns.contains(&&Pattern::Literal(Expr::StringLiteral(v.to_string()))))
+            .map(|v| format!("\"{}\"", v))
+            .collect();
+        if !missing.is_empty() {
+            parse_fail!(self, "match no exhaustivo sobre {}, faltan: {}", name, missing.join(", "));
+        }
+        Some(())
     }
 
     // Parse para las expresiones atómicas (valores literales y paréntesis)
@@ -107,19 +872,85 @@ impl<'a> Parser<'a> {
                 self.advance();
                 Some(Expr::BooleanLiteral(false))
             }
+            Token::Null => {
+                self.advance();
+                Some(Expr::NullLiteral)
+            }
             Token::StringLiteral(s) => {
                 let str_token = Some(Expr::StringLiteral(s.to_string()));
                 self.advance();
                 str_token
             }
+            Token::InterpolatedString(parts) => {
+                let parts = parts.clone();
+                self.advance();
+                self.parse_interpolation(&parts)
+            }
+            Token::Number(n) => {
+                let number = Expr::NumberLiteral(*n);
+                self.advance();
+                Some(number)
+            }
+            Token::Integer(n) => {
+                let integer = Expr::IntegerLiteral(*n);
+                self.advance();
+                Some(integer)
+            }
+            Token::Decimal(n) => {
+                let decimal = Expr::DecimalLiteral(*n);
+                self.advance();
+                Some(decimal)
+            }
+            #[cfg(feature = "bignum")]
+            Token::Rational(n) => {
+                let rational = Expr::RationalLiteral(n.clone());
+                self.advance();
+                Some(rational)
+            }
+            Token::Identifier(name) => {
+                let mut name = name.to_string();
+                self.advance();
+                // Nombres cualificados con el espacio de nombres de un módulo: geo::distance
+                while let Some(Token::PathSep) = self.peek() {
+                    self.advance(); // Consumiendo "::"
+                    match self.advance() {
+                        Some(Token::Identifier(part)) => {
+                            name.push_str("::");
+                            name.push_str(part);
+                        }
+                        _ => parse_fail!(self, "Se esperaba un nombre después de '::'"),
+                    }
+                }
+                if let Some(Token::LParen) = self.peek() {
+                    let args = self.parse_arguments()?;
+                    return self.regex_call(name, args);
+                }
+                // Lambda de un solo parámetro: x -> cuerpo
+                if let Some(Token::Arrow) = self.peek() {
+                    self.advance(); // Consumiendo "->"
+                    let body = self.parse_expression()?;
+                    return Some(Expr::Lambda(vec![name], Box::new(body)));
+                }
+                Some(Expr::Variable(name))
+            }
+            Token::If => self.parse_if(),
+            Token::Case => self.parse_case(),
+            Token::Match => self.parse_match(),
+            Token::Let => self.parse_let(),
+            Token::LBracket => self.parse_list(),
             Token::LParen => {
+                // Lambda con varios parámetros: (a, b) -> cuerpo
+                if let Some(params) = self.parse_lambda_params() {
+                    let body = self.parse_expression()?;
+                    return Some(Expr::Lambda(params, Box::new(body)));
+                }
                 self.advance(); // Consumiendo "("
-                let expr = self.parse()?; // parse sub-expresión
+                let expr = self.parse_nested()?; // parse sub-expresión
                 if let Some(Token::RParen) = self.peek() {
                     self.advance(); // Consumiendo ")"
                     Some(expr)
                 } else {
-                    panic!("Error: No se encontró un paréntesis de cierre"); // Si no se encuentra el paréntesis de cierre
+                    parse_fail!(self, "No se encontró un paréntesis de cierre"); // Si no se encuentra el paréntesis de cierre
                 }
             }
             _ => None,
@@ -127,6 +958,23 @@ impl<'a> Parser<'a> {
     }
 }
 
+// Tokens con los que puede empezar un operando (los que acepta parse_not)
+fn starts_operand(token: Option<&Token>) -> bool {
+    #[cfg(feature = "bignum")]
+    if let Some(Token::Rational(_)) = token {
+        return true;
+    }
+    matches!(
+        token,
+        Some(
+            Token::True | Token::False | Token::Null | Token::StringLiteral(_) | Token::InterpolatedString(_)
+                | Token::Number(_) | Token::Integer(_) | Token::Decimal(_) | Token::Identifier(_) | Token::If
+                | Token::Case | Token::Match | Token::Let | Token::LBracket | Token::LParen | Token::Not
+                | Token::Minus | Token::BitNot
+        )
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +1018,29 @@ mod tests {
         parser.parse().unwrap(); // Debería fallar.
     }
 
+    #[test]
+    fn test_try_parse_returns_syntax_errors() {
+        let tokens = tokenize("(true && false");
+        assert_eq!(
+            Parser::new(&tokens).try_parse(),
+            Err(ExprError::Syntax("No se encontró un paréntesis de cierre".to_string()))
+        );
+        let tokens = tokenize("x = 1; if x > 0 then 1");
+        assert_eq!(
+            Parser::new(&tokens).try_parse_program(),
+            Err(ExprError::Syntax("Se esperaba 'else' en la expresión if".to_string()))
+        );
+        // Los errores dentro de una interpolación también se devuelven
+        let tokens = tokenize(r#""total: ${(1 + 2}""#);
+        assert_eq!(
+            Parser::new(&tokens).try_parse(),
+            Err(ExprError::Syntax("No se encontró un paréntesis de cierre".to_string()))
+        );
+        let tokens = tokenize("1 +");
+        assert_eq!(Parser::new(&tokens).try_parse(), Err(ExprError::Syntax("Not valid expresion".to_string())));
+        assert_eq!(Parser::new(&[]).try_parse_program(), Ok(vec![]));
+    }
+
     #[test]
     fn test_parser_string_comparison() {
         let tokens = tokenize(r#""hello" == "world""#);
@@ -196,4 +1067,574 @@ mod tests {
         assert_eq!(expr, expected);
     }
 
+    #[test]
+    fn test_parser_ternary() {
+        let tokens = tokenize(r#"true || false ? "gold" : "standard""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Conditional(
+            Box::new(Expr::Or(
+                Box::new(Expr::BooleanLiteral(true)),
+                Box::new(Expr::BooleanLiteral(false)),
+            )),
+            Box::new(Expr::StringLiteral("gold".to_string())),
+            Box::new(Expr::StringLiteral("standard".to_string())),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_ternary_right_associative() {
+        let tokens = tokenize(r#"false ? "a" : true ? "b" : "c""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Conditional(
+            Box::new(Expr::BooleanLiteral(false)),
+            Box::new(Expr::StringLiteral("a".to_string())),
+            Box::new(Expr::Conditional(
+                Box::new(Expr::BooleanLiteral(true)),
+                Box::new(Expr::StringLiteral("b".to_string())),
+                Box::new(Expr::StringLiteral("c".to_string())),
+            )),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba ':' en el operador ternario")]
+    fn test_parser_ternary_missing_colon() {
+        let tokens = tokenize(r#"true ? "a""#);
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parser_comparison_precedence() {
+        let tokens = tokenize("x > -1 == true");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Equal(
+            Box::new(Expr::Greater(
+                Box::new(Expr::Variable("x".to_string())),
+                Box::new(Expr::Negate(Box::new(Expr::IntegerLiteral(1)))),
+            )),
+            Box::new(Expr::BooleanLiteral(true)),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_if_else_if() {
+        let tokens = tokenize(r#"if a then "x" else if b then "y" else "z""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Conditional(
+            Box::new(Expr::Variable("a".to_string())),
+            Box::new(Expr::StringLiteral("x".to_string())),
+            Box::new(Expr::Conditional(
+                Box::new(Expr::Variable("b".to_string())),
+                Box::new(Expr::StringLiteral("y".to_string())),
+                Box::new(Expr::StringLiteral("z".to_string())),
+            )),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_case_when() {
+        let tokens = tokenize(r#"case when x > 10 then "big" when x > 5 then "mid" else "small" end"#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Case(
+            vec![
+                (
+                    Expr::Greater(Box::new(Expr::Variable("x".to_string())), Box::new(Expr::IntegerLiteral(10))),
+                    Expr::StringLiteral("big".to_string()),
+                ),
+                (
+                    Expr::Greater(Box::new(Expr::Variable("x".to_string())), Box::new(Expr::IntegerLiteral(5))),
+                    Expr::StringLiteral("mid".to_string()),
+                ),
+            ],
+            Some(Box::new(Expr::StringLiteral("small".to_string()))),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba 'end' al final de la expresión case")]
+    fn test_parser_case_without_end() {
+        let tokens = tokenize(r#"case when true then "a" else "b""#);
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba 'else' en la expresión if")]
+    fn test_parser_if_without_else() {
+        let tokens = tokenize(r#"if true then "a""#);
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parser_match() {
+        let tokens = tokenize(r#"match status { "active" | "trial" => 1, "churned" => 0, _ => -1 }"#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Match(
+            Box::new(Expr::Variable("status".to_string())),
+            vec![
+                (
+                    vec![
+                        Pattern::Literal(Expr::StringLiteral("active".to_string())),
+                        Pattern::Literal(Expr::StringLiteral("trial".to_string())),
+                    ],
+                    Expr::IntegerLiteral(1),
+                ),
+                (vec![Pattern::Literal(Expr::StringLiteral("churned".to_string()))], Expr::IntegerLiteral(0)),
+                (vec![Pattern::Wildcard], Expr::Negate(Box::new(Expr::IntegerLiteral(1)))),
+            ],
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_match_exhaustive_with_schema() {
+        let mut schema = Schema::new();
+        schema.add_enum("status", &["active", "trial", "churned"]);
+        let tokens = tokenize(r#"match status { "active" | "trial" => 1, "churned" => 0 }"#);
+        let mut parser = Parser::with_schema(&tokens, &schema);
+        assert!(parser.parse().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: match no exhaustivo sobre status, faltan: \"churned\"")]
+    fn test_parser_match_not_exhaustive_with_schema() {
+        let mut schema = Schema::new();
+        schema.add_enum("status", &["active", "trial", "churned"]);
+        let tokens = tokenize(r#"match status { "active" | "trial" => 1 }"#);
+        let mut parser = Parser::with_schema(&tokens, &schema);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: \"paused\" no es un valor posible de status")]
+    fn test_parser_match_unknown_value_with_schema() {
+        let mut schema = Schema::new();
+        schema.add_enum("status", &["active"]);
+        let tokens = tokenize(r#"match status { "active" => 1, "paused" => 2 }"#);
+        let mut parser = Parser::with_schema(&tokens, &schema);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parser_bitwise_and_power_precedence() {
+        let int = |n| Box::new(Expr::IntegerLiteral(n));
+        let parse = |input| {
+            let tokens = tokenize(input);
+            Parser::new(&tokens).parse().unwrap()
+        };
+
+        // Los operadores de bits son más fuertes que las comparaciones y más débiles que la suma
+        assert_eq!(
+            parse("flags & 1 << 2 + 1 == 0"),
+            Expr::Equal(
+                Box::new(Expr::BitAnd(
+                    Box::new(Expr::Variable("flags".to_string())),
+                    Box::new(Expr::ShiftLeft(int(1), Box::new(Expr::Add(int(2), int(1))))),
+                )),
+                int(0),
+            )
+        );
+        assert_eq!(
+            parse("1 | 2 ^ 3 & 4"),
+            Expr::BitOr(int(1), Box::new(Expr::BitXor(int(2), Box::new(Expr::BitAnd(int(3), int(4))))))
+        );
+        // La potencia es asociativa por la derecha y más fuerte que el menos unario
+        assert_eq!(parse("2 ** 3 ** 2"), Expr::Power(int(2), Box::new(Expr::Power(int(3), int(2)))));
+        assert_eq!(parse("-2 ** 2"), Expr::Negate(Box::new(Expr::Power(int(2), int(2)))));
+        assert_eq!(parse("2 ** -1"), Expr::Power(int(2), Box::new(Expr::Negate(int(1)))));
+        assert_eq!(
+            parse("7 // 2 % 3 * ~x"),
+            Expr::Multiply(
+                Box::new(Expr::Modulo(Box::new(Expr::IntDivide(int(7), int(2))), int(3))),
+                Box::new(Expr::BitNot(Box::new(Expr::Variable("x".to_string())))),
+            )
+        );
+        // "%" es un porcentaje si no le sigue un operando y el resto si le sigue, con o sin espacios
+        let percent = |expr: Box<Expr>| Expr::Divide(expr, int(100));
+        let x = || Box::new(Expr::Variable("x".to_string()));
+        assert_eq!(parse("100 / 15%"), Expr::Divide(int(100), Box::new(percent(int(15)))));
+        assert_eq!(parse("x% * 2"), Expr::Multiply(Box::new(percent(x())), int(2)));
+        assert_eq!(parse("f(5 %)"), Expr::Call("f".to_string(), vec![percent(int(5))]));
+        for input in ["7%-2", "7 % -2", "7 %-2", "7% -2"] {
+            assert_eq!(parse(input), Expr::Modulo(int(7), Box::new(Expr::Negate(int(2)))), "{}", input);
+        }
+        assert_eq!(parse("x%-2"), Expr::Modulo(x(), Box::new(Expr::Negate(int(2)))));
+        assert_eq!(parse("15% - 2"), Expr::Modulo(int(15), Box::new(Expr::Negate(int(2)))));
+        assert_eq!(parse("15% + 2"), Expr::Add(Box::new(percent(int(15))), int(2)));
+    }
+
+    #[test]
+    fn test_parser_arithmetic_precedence() {
+        let tokens = tokenize("1 + 2 * 3 - 4");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Subtract(
+            Box::new(Expr::Add(
+                Box::new(Expr::IntegerLiteral(1)),
+                Box::new(Expr::Multiply(
+                    Box::new(Expr::IntegerLiteral(2)),
+                    Box::new(Expr::IntegerLiteral(3)),
+                )),
+            )),
+            Box::new(Expr::IntegerLiteral(4)),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_let() {
+        let tokens = tokenize("let a = 1, b = a in a + b > 1");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Let(
+            "a".to_string(),
+            Box::new(Expr::IntegerLiteral(1)),
+            Box::new(Expr::Let(
+                "b".to_string(),
+                Box::new(Expr::Variable("a".to_string())),
+                Box::new(Expr::Greater(
+                    Box::new(Expr::Add(
+                        Box::new(Expr::Variable("a".to_string())),
+                        Box::new(Expr::Variable("b".to_string())),
+                    )),
+                    Box::new(Expr::IntegerLiteral(1)),
+                )),
+            )),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba 'in' después de los valores del let")]
+    fn test_parser_let_without_in() {
+        let tokens = tokenize("let a = 1 a");
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Token inesperado después de la expresión: False")]
+    fn test_parser_trailing_tokens() {
+        let tokens = tokenize("true false true");
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parser_program() {
+        let tokens = tokenize("x = 1; y = x + 1; x < y;");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        let expected = vec![
+            Expr::Assign("x".to_string(), Box::new(Expr::IntegerLiteral(1))),
+            Expr::Assign(
+                "y".to_string(),
+                Box::new(Expr::Add(
+                    Box::new(Expr::Variable("x".to_string())),
+                    Box::new(Expr::IntegerLiteral(1)),
+                )),
+            ),
+            Expr::Less(
+                Box::new(Expr::Variable("x".to_string())),
+                Box::new(Expr::Variable("y".to_string())),
+            ),
+        ];
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba ';' entre sentencias y se encontró Integer(2)")]
+    fn test_parser_program_missing_semicolon() {
+        let tokens = tokenize("x = 1 2");
+        let mut parser = Parser::new(&tokens);
+        parser.parse_program().unwrap();
+    }
+
+    #[test]
+    fn test_parser_function_definition_and_call() {
+        let tokens = tokenize("fn is_adult(a) = a >= 18; is_adult(edad)");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        let expected = vec![
+            Expr::FunctionDef(
+                "is_adult".to_string(),
+                vec!["a".to_string()],
+                Box::new(Expr::GreaterEqual(
+                    Box::new(Expr::Variable("a".to_string())),
+                    Box::new(Expr::IntegerLiteral(18)),
+                )),
+            ),
+            Expr::Call("is_adult".to_string(), vec![Expr::Variable("edad".to_string())]),
+        ];
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba '=' antes del cuerpo de la función")]
+    fn test_parser_function_definition_without_body() {
+        let tokens = tokenize("fn f(a, b) a");
+        let mut parser = Parser::new(&tokens);
+        parser.parse_program().unwrap();
+    }
+
+    #[test]
+    fn test_parser_import_and_qualified_names() {
+        let tokens = tokenize(r#"import "common/geo.rex"; import "pricing" as p; geo::distance(a, p::rate)"#);
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        let expected = vec![
+            Expr::Import("common/geo.rex".to_string(), None),
+            Expr::Import("pricing".to_string(), Some("p".to_string())),
+            Expr::Call(
+                "geo::distance".to_string(),
+                vec![Expr::Variable("a".to_string()), Expr::Variable("p::rate".to_string())],
+            ),
+        ];
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn test_parser_lambdas() {
+        let tokens = tokenize("any(items, x -> x > 100)");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Call(
+            "any".to_string(),
+            vec![
+                Expr::Variable("items".to_string()),
+                Expr::Lambda(
+                    vec!["x".to_string()],
+                    Box::new(Expr::Greater(
+                        Box::new(Expr::Variable("x".to_string())),
+                        Box::new(Expr::IntegerLiteral(100)),
+                    )),
+                ),
+            ],
+        );
+        assert_eq!(expr, expected);
+
+        let tokens = tokenize("(a, b) -> a + b");
+        let mut parser = Parser::new(&tokens);
+        let expected = Expr::Lambda(
+            vec!["a".to_string(), "b".to_string()],
+            Box::new(Expr::Add(
+                Box::new(Expr::Variable("a".to_string())),
+                Box::new(Expr::Variable("b".to_string())),
+            )),
+        );
+        assert_eq!(parser.parse().unwrap(), expected);
+
+        // Un paréntesis normal sigue siendo una sub-expresión
+        let tokens = tokenize("(a) + 1");
+        let mut parser = Parser::new(&tokens);
+        assert!(matches!(parser.parse().unwrap(), Expr::Add(_, _)));
+    }
+
+    #[test]
+    fn test_parser_lists_index_and_membership() {
+        let tokens = tokenize(r#"country in ["ES", "PT"] && codes[1] not in []"#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::And(
+            Box::new(Expr::In(
+                Box::new(Expr::Variable("country".to_string())),
+                Box::new(Expr::ListLiteral(vec![
+                    Expr::StringLiteral("ES".to_string()),
+                    Expr::StringLiteral("PT".to_string()),
+                ])),
+            )),
+            Box::new(Expr::NotIn(
+                Box::new(Expr::Index(
+                    Box::new(Expr::Variable("codes".to_string())),
+                    Box::new(Expr::IntegerLiteral(1)),
+                )),
+                Box::new(Expr::ListLiteral(vec![])),
+            )),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_let_value_stops_at_in() {
+        // El primer "in" cierra el valor del let; entre paréntesis vuelve a ser pertenencia
+        let tokens = tokenize("let ok = (x in l) in ok && x in l");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let membership = Expr::In(
+            Box::new(Expr::Variable("x".to_string())),
+            Box::new(Expr::Variable("l".to_string())),
+        );
+        let expected = Expr::Let(
+            "ok".to_string(),
+            Box::new(membership.clone()),
+            Box::new(Expr::And(Box::new(Expr::Variable("ok".to_string())), Box::new(membership))),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_member_access() {
+        let tokens = tokenize(r#"user.address.city == attrs["x-flag"]"#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Equal(
+            Box::new(Expr::Member(
+                Box::new(Expr::Member(
+                    Box::new(Expr::Variable("user".to_string())),
+                    "address".to_string(),
+                )),
+                "city".to_string(),
+            )),
+            Box::new(Expr::Index(
+                Box::new(Expr::Variable("attrs".to_string())),
+                Box::new(Expr::StringLiteral("x-flag".to_string())),
+            )),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_null_operators() {
+        let tokens = tokenize("user?.age ?? 0 > 18 && name is not null");
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::And(
+            Box::new(Expr::Greater(
+                Box::new(Expr::Coalesce(
+                    Box::new(Expr::SafeMember(
+                        Box::new(Expr::Variable("user".to_string())),
+                        "age".to_string(),
+                    )),
+                    Box::new(Expr::IntegerLiteral(0)),
+                )),
+                Box::new(Expr::IntegerLiteral(18)),
+            )),
+            Box::new(Expr::IsNotNull(Box::new(Expr::Variable("name".to_string())))),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Se esperaba 'null' después de 'is'")]
+    fn test_parser_is_without_null() {
+        let tokens = tokenize("x is true");
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parser_regex_literals_are_compiled() {
+        let tokens = tokenize(r#"email =~ r"^[^@]+@corp\.com$" && matches(code, pattern)"#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::And(
+            Box::new(Expr::RegexMatch(
+                Box::new(Expr::Variable("email".to_string())),
+                Box::new(Expr::Regex(CompiledRegex::try_new(r"^[^@]+@corp\.com$").unwrap())),
+            )),
+            Box::new(Expr::Call(
+                "matches".to_string(),
+                vec![Expr::Variable("code".to_string()), Expr::Variable("pattern".to_string())],
+            )),
+        );
+        assert_eq!(expr, expected);
+
+        let tokens = tokenize(r#"captures(code, r"(\d+)")"#);
+        let expected = Expr::Call(
+            "captures".to_string(),
+            vec![Expr::Variable("code".to_string()), Expr::Regex(CompiledRegex::try_new(r"(\d+)").unwrap())],
+        );
+        assert_eq!(Parser::new(&tokens).parse().unwrap(), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Expresión regular no válida \"[a-\"")]
+    fn test_parser_invalid_regex_literal() {
+        let tokens = tokenize(r#"code =~ "[a-""#);
+        Parser::new(&tokens).parse();
+    }
+
+    #[test]
+    fn test_parser_invalid_regex_literal_is_a_syntax_error() {
+        let tokens = tokenize(r#"x =~ "(""#);
+        let error = Parser::new(&tokens).try_parse().unwrap_err();
+        assert!(matches!(&error, ExprError::Syntax(message) if message.starts_with("Expresión regular no válida \"(\"")), "{}", error);
+        let tokens = tokenize(r#"matches(x, "(")"#);
+        assert!(matches!(Parser::new(&tokens).try_parse(), Err(ExprError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_parser_wildcard_operators() {
+        let tokens = tokenize(r#"name ilike "jo%" || path not glob "*.log""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Or(
+            Box::new(Expr::ILike(
+                Box::new(Expr::Variable("name".to_string())),
+                Box::new(Expr::StringLiteral("jo%".to_string())),
+            )),
+            Box::new(Expr::Not(Box::new(Expr::Glob(
+                Box::new(Expr::Variable("path".to_string())),
+                Box::new(Expr::StringLiteral("*.log".to_string())),
+            )))),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn test_parser_interpolation() {
+        let tokens = tokenize(r#""Hola ${user.name}, debes ${amount * 2}""#);
+        let mut parser = Parser::new(&tokens);
+        let expr = parser.parse().unwrap();
+
+        let expected = Expr::Interpolate(vec![
+            Expr::StringLiteral("Hola ".to_string()),
+            Expr::Member(Box::new(Expr::Variable("user".to_string())), "name".to_string()),
+            Expr::StringLiteral(", debes ".to_string()),
+            Expr::Multiply(
+                Box::new(Expr::Variable("amount".to_string())),
+                Box::new(Expr::IntegerLiteral(2)),
+            ),
+        ]);
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Interpolación vacía en la cadena")]
+    fn test_parser_empty_interpolation() {
+        let tokens = tokenize(r#""Hola ${}""#);
+        Parser::new(&tokens).parse();
+    }
+
 }
@@ -0,0 +1,269 @@
+use crate::modules::error::{fail, ExprError};
+use crate::modules::functions::ValueType::{Float, Int, List, Number};
+use crate::modules::functions::{list_arg, Builtin};
+use crate::modules::interprete::{add, compare_numbers, divide, finite, Context, EvalResult};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+// Estadísticas sobre listas, para reglas como avg(latencies) > 200. Los null de la lista se
+// ignoran, como en los agregados de SQL. Sin valores, sum y distinct_count dan 0 y el resto un
+// error, o null si el contexto lo pide con set_null_on_empty_list.
+pub(crate) const BUILTINS: &[Builtin] = &[
+    Builtin { name: "sum", params: &[List], required: 1, returns: Number, call: sum },
+    Builtin { name: "avg", params: &[List], required: 1, returns: Number, call: avg },
+    Builtin { name: "median", params: &[List], required: 1, returns: Number, call: median },
+    Builtin { name: "percentile", params: &[List, Number], required: 2, returns: Number, call: percentile },
+    Builtin { name: "stddev", params: &[List], required: 1, returns: Float, call: stddev },
+    Builtin { name: "min_of", params: &[List], required: 1, returns: Number, call: min_of },
+    Builtin { name: "max_of", params: &[List], required: 1, returns: Number, call: max_of },
+    Builtin { name: "distinct_count", params: &[List], required: 1, returns: Int, call: distinct_count },
+];
+
+fn sum(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    total(&numbers("sum", &args[0])?)
+}
+
+// Media: la suma dividida entre el número de valores, con la misma promoción que / (la media de
+// enteros es un decimal y la de decimales exactos es exacta)
+fn avg(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let numbers = numbers("avg", &args[0])?;
+    if numbers.is_empty() {
+        return not_enough("avg", 1, context);
+    }
+    divide(total(&numbers)?, EvalResult::Int(numbers.len() as i64))
+}
+
+fn median(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    quantile("median", &args[0], Decimal::from(50), context)
+}
+
+// percentile(lista, p) con p entre 0 y 100: percentile(latencies, 99)
+fn percentile(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let percent = args[1].as_decimal().or_else(|| args[1].as_float().and_then(|p| Decimal::try_from(p).ok()));
+    match percent {
+        Some(percent) if percent >= Decimal::ZERO && percent <= Decimal::ONE_HUNDRED => {
+            quantile("percentile", &args[0], percent, context)
+        }
+        _ => fail!("percentile espera un percentil entre 0 y 100 y recibió {}", args[1]),
+    }
+}
+
+// Desviación típica muestral (divide entre n - 1, como DESVEST de las hojas de cálculo). Se usa el
+// algoritmo de Welford, que no pierde precisión cuando la media es grande frente a la dispersión.
+fn stddev(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    let numbers = numbers("stddev", &args[0])?;
+    if numbers.len() < 2 {
+        return not_enough("stddev", 2, context);
+    }
+    let (mut mean, mut squares) = (0.0, 0.0);
+    for (i, value) in numbers.iter().filter_map(EvalResult::as_float).enumerate() {
+        let delta = value - mean;
+        mean += delta / (i + 1) as f64;
+        squares += delta * (value - mean);
+    }
+    finite((squares / (numbers.len() - 1) as f64).sqrt(), || format!("stddev({})", args[0]))
+}
+
+fn min_of(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    extreme("min_of", &args[0], Ordering::Less, context)
+}
+
+fn max_of(args: Vec<EvalResult>, context: &mut Context) -> Result<EvalResult, ExprError> {
+    extreme("max_of", &args[0], Ordering::Greater, context)
+}
+
+// Valores distintos de cualquier tipo; los números se comparan por su valor (1 y 1.0 son el mismo)
+fn distinct_count(args: Vec<EvalResult>, _: &mut Context) -> Result<EvalResult, ExprError> {
+    let mut numbers = Vec::new();
+    let mut texts = HashSet::new();
+    let mut others: Vec<&EvalResult> = Vec::new();
+    for item in list_arg(&args[0]) {
+        match item {
+            EvalResult::Null => {}
+            EvalResult::String(text) => {
+                texts.insert(text.as_str());
+            }
+            _ if item.is_number() => numbers.push(item),
+            _ if !others.contains(&item) => others.push(item),
+            _ => {}
+        }
+    }
+    numbers.sort_by(|a, b| compare_numbers(a, b).unwrap_or(Ordering::Equal));
+    numbers.dedup_by(|a, b| compare_numbers(a, b) == Some(Ordering::Equal));
+    Ok(EvalResult::Int((numbers.len() + texts.len() + others.len()) as i64))
+}
+
+// Números de la lista, sin los null
+fn numbers(name: &str, list: &EvalResult) -> Result<Vec<EvalResult>, ExprError> {
+    let mut numbers = Vec::new();
+    for item in list_arg(list) {
+        match item {
+            EvalResult::Null => {}
+            _ if item.is_number() => numbers.push(item.clone()),
+            other => fail!("{} espera una lista de números y recibió {}", name, other),
+        }
+    }
+    Ok(numbers)
+}
+
+// Lista sin valores suficientes para calcular la estadística
+fn not_enough(name: &str, needed: usize, context: &Context) -> Result<EvalResult, ExprError> {
+    match context.null_on_empty_list() {
+        true => Ok(EvalResult::Null),
+        false if needed == 1 => fail!("No se puede calcular {} de una lista vacía", name),
+        false => fail!("{} necesita al menos {} valores", name, needed),
+    }
+}
+
+// Suma exacta de enteros y decimales exactos. Los f64 se suman con el algoritmo de Neumaier, que
+// compensa el error de redondeo de cada suma: sum([0.1, 0.1, ...]) con diez valores da 1.
+fn total(numbers: &[EvalResult]) -> Result<EvalResult, ExprError> {
+    let floats = numbers.iter().any(|n| matches!(n, EvalResult::Float(_)));
+    let decimals = numbers.iter().any(|n| matches!(n, EvalResult::Decimal(_)));
+    if !floats || decimals {
+        // Mezclar decimales exactos y f64 da el mismo error que con +
+        return numbers.iter().cloned().try_fold(EvalResult::Int(0), add);
+    }
+    let (mut total, mut compensation) = (0.0_f64, 0.0);
+    for value in numbers.iter().filter_map(EvalResult::as_float) {
+        let next = total + value;
+        compensation += match total.abs() >= value.abs() {
+            true => (total - next) + value,
+            false => (value - next) + total,
+        };
+        total = next;
+    }
+    finite(total + compensation, || "sum".to_string())
+}
+
+// Percentil con interpolación lineal entre los dos valores más cercanos (PERCENTIL.INC de las
+// hojas de cálculo): median([1, 2, 3, 4]) es 2.5. Si cae justo en un valor se devuelve tal cual.
+fn quantile(name: &str, list: &EvalResult, percent: Decimal, context: &Context) -> Result<EvalResult, ExprError> {
+    let mut numbers = numbers(name, list)?;
+    if numbers.is_empty() {
+        return not_enough(name, 1, context);
+    }
+    numbers.sort_by(|a, b| compare_numbers(a, b).unwrap_or(Ordering::Equal));
+    let rank = percent * Decimal::from(numbers.len() - 1) / Decimal::ONE_HUNDRED;
+    let index = usize::try_from(rank.floor()).unwrap_or(0);
+    let fraction = rank - rank.floor();
+    let lower = &numbers[index];
+    if fraction.is_zero() {
+        return Ok(lower.clone());
+    }
+    let upper = &numbers[index + 1];
+    let exact = matches!(lower, EvalResult::Decimal(_)) || matches!(upper, EvalResult::Decimal(_));
+    match (lower.as_decimal(), upper.as_decimal()) {
+        (Some(l), Some(u)) if exact => Ok(EvalResult::Decimal(l + (u - l) * fraction)),
+        _ => {
+            let (l, u) = (lower.as_float().unwrap_or(0.0), upper.as_float().unwrap_or(0.0));
+            finite(l + (u - l) * f64::try_from(fraction).unwrap_or(0.0), || format!("{}({})", name, list))
+        }
+    }
+}
+
+// El menor o el mayor valor de la lista, sin convertirlo
+fn extreme(name: &str, list: &EvalResult, wanted: Ordering, context: &Context) -> Result<EvalResult, ExprError> {
+    let best = numbers(name, list)?
+        .into_iter()
+        .reduce(|best, item| match compare_numbers(&item, &best) == Some(wanted) {
+            true => item,
+            false => best,
+        });
+    match best {
+        Some(best) => Ok(best),
+        None => not_enough(name, 1, context),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::error::ExprError;
+    use crate::modules::functions::call_builtin;
+    use crate::modules::interprete::{Context, EvalResult};
+    use rust_decimal::Decimal;
+
+    fn floats(values: &[f64]) -> EvalResult {
+        EvalResult::List(values.iter().map(|v| EvalResult::Float(*v)).collect())
+    }
+
+    fn ints(values: &[i64]) -> EvalResult {
+        EvalResult::List(values.iter().map(|v| EvalResult::Int(*v)).collect())
+    }
+
+    fn call(name: &str, args: Vec<EvalResult>) -> Result<EvalResult, ExprError> {
+        call_builtin(name, args, &mut Context::new()).unwrap()
+    }
+
+    #[test]
+    fn test_sum_is_compensated() {
+        assert_eq!(call("sum", vec![floats(&[0.1; 10])]), Ok(EvalResult::Float(1.0)));
+        assert_eq!(call("sum", vec![floats(&[1e100, 1.0, -1e100])]), Ok(EvalResult::Float(1.0)));
+        assert_eq!(call("sum", vec![ints(&[1, 2, 3])]), Ok(EvalResult::Int(6)));
+        assert_eq!(call("sum", vec![ints(&[])]), Ok(EvalResult::Int(0)));
+        let prices = EvalResult::List(vec![EvalResult::Decimal(Decimal::new(1999, 2)), EvalResult::Null, EvalResult::Int(1)]);
+        assert_eq!(call("sum", vec![prices]), Ok(EvalResult::Decimal(Decimal::new(2099, 2))));
+    }
+
+    #[test]
+    fn test_stddev_is_stable() {
+        // Desviación de 4, 7, 13 y 16 desplazados mil millones: sqrt(30)
+        let shifted: Vec<f64> = [4.0, 7.0, 13.0, 16.0].iter().map(|v| v + 1e9).collect();
+        match call("stddev", vec![floats(&shifted)]) {
+            Ok(EvalResult::Float(stddev)) => assert!((stddev - 30f64.sqrt()).abs() < 1e-9, "{}", stddev),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let latencies = ints(&[120, 80, 300, 95, 150]);
+        assert_eq!(call("median", vec![latencies.clone()]), Ok(EvalResult::Int(120)));
+        assert_eq!(call("median", vec![ints(&[1, 2, 3, 4])]), Ok(EvalResult::Float(2.5)));
+        assert_eq!(call("percentile", vec![latencies.clone(), EvalResult::Int(0)]), Ok(EvalResult::Int(80)));
+        assert_eq!(call("percentile", vec![latencies.clone(), EvalResult::Int(90)]), Ok(EvalResult::Float(240.0)));
+        let amounts = EvalResult::List(vec![EvalResult::Decimal(Decimal::new(10, 0)), EvalResult::Decimal(Decimal::new(15, 0))]);
+        assert_eq!(call("median", vec![amounts]), Ok(EvalResult::Decimal(Decimal::new(125, 1))));
+        assert_eq!(
+            call("percentile", vec![latencies, EvalResult::Int(101)]),
+            Err(ExprError::Runtime("percentile espera un percentil entre 0 y 100 y recibió 101".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_extremes_and_distinct() {
+        let values = EvalResult::List(vec![EvalResult::Int(3), EvalResult::Float(-1.5), EvalResult::Null, EvalResult::Int(7)]);
+        assert_eq!(call("min_of", vec![values.clone()]), Ok(EvalResult::Float(-1.5)));
+        assert_eq!(call("max_of", vec![values]), Ok(EvalResult::Int(7)));
+        let text = |value: &str| EvalResult::String(value.to_string());
+        let mixed = EvalResult::List(vec![EvalResult::Int(1), EvalResult::Float(1.0), text("a"), text("a"), EvalResult::Bool(true), EvalResult::Null]);
+        assert_eq!(call("distinct_count", vec![mixed]), Ok(EvalResult::Int(3)));
+    }
+
+    #[test]
+    fn test_empty_lists() {
+        assert_eq!(
+            call("avg", vec![ints(&[])]),
+            Err(ExprError::Runtime("No se puede calcular avg de una lista vacía".to_string()))
+        );
+        assert_eq!(
+            call("stddev", vec![ints(&[5])]),
+            Err(ExprError::Runtime("stddev necesita al menos 2 valores".to_string()))
+        );
+        let mut context = Context::new();
+        context.set_null_on_empty_list(true);
+        let only_nulls = EvalResult::List(vec![EvalResult::Null]);
+        assert_eq!(call_builtin("max_of", vec![only_nulls], &mut context), Some(Ok(EvalResult::Null)));
+        assert_eq!(call_builtin("median", vec![ints(&[])], &mut context), Some(Ok(EvalResult::Null)));
+    }
+
+    #[test]
+    fn test_non_numeric_items() {
+        let items = EvalResult::List(vec![EvalResult::Int(1), EvalResult::Bool(true)]);
+        assert_eq!(
+            call("avg", vec![items]),
+            Err(ExprError::Runtime("avg espera una lista de números y recibió true".to_string()))
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::modules::error::{fail, ExprError};
+use crate::modules::interprete::{try_evaluate_with, Context, EvalResult};
+use crate::modules::parser::{Expr, Parser};
+use crate::modules::tokenizer::try_tokenize;
+
+// Termina el análisis de la plantilla con un ExprError::Syntax con el mensaje indicado
+macro_rules! template_fail {
+    ($($arg:tt)*) => {
+        return Err(ExprError::Syntax(format!($($arg)*)))
+    };
+}
+
+// Nodos de una plantilla ya analizada
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),                       // texto que se copia tal cual
+    Output(Expr),                       // {{ expresión }}
+    If(Expr, Vec<Node>, Vec<Node>),     // {% if cond %} ... {% else %} ... {% endif %}
+}
+
+// Plantilla de texto con {{ expresiones }} y bloques {% if %}; se analiza una vez y se
+// puede renderizar con distintos contextos
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    // Una plantilla mal formada (etiquetas sin cerrar, expresiones no válidas) es un ExprError::Syntax
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let mut reader = TemplateReader { source, pos: 0 };
+        let (nodes, end) = reader.read_nodes()?;
+        if let Some(tag) = end {
+            template_fail!("{{% {} %}} sin {{% if %}} en la plantilla", tag);
+        }
+        Ok(Template { nodes })
+    }
+
+    pub fn render(&self, context: &mut Context) -> Result<String, ExprError> {
+        let mut output = String::new();
+        render_nodes(&self.nodes, context, &mut output)?;
+        Ok(output)
+    }
+}
+
+// Analiza y renderiza una plantilla en un solo paso
+pub fn render(source: &str, context: &mut Context) -> Result<String, ExprError> {
+    Template::parse(source)?.render(context)
+}
+
+fn render_nodes(nodes: &[Node], context: &mut Context, output: &mut String) -> Result<(), ExprError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Output(expr) => output.push_str(&try_evaluate_with(expr.clone(), context)?.to_string()),
+            Node::If(condition, then_nodes, else_nodes) => match try_evaluate_with(condition.clone(), context)? {
+                EvalResult::Bool(true) => render_nodes(then_nodes, context, output)?,
+                // Igual que en las expresiones, una condición null no se cumple
+                EvalResult::Bool(false) | EvalResult::Null => render_nodes(else_nodes, context, output)?,
+                other => fail!("La condición de {{% if %}} debe ser un booleano: {:?}", other),
+            },
+        }
+    }
+    Ok(())
+}
+
+// Lector del texto de la plantilla
+struct TemplateReader<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl TemplateReader<'_> {
+    // Lee nodos hasta el final o hasta una etiqueta de cierre ({% else %} o {% endif %}),
+    // que se devuelve para que la trate el bloque que la espera
+    fn read_nodes(&mut self) -> Result<(Vec<Node>, Option<String>), ExprError> {
+        let mut nodes = Vec::new();
+        loop {
+            let rest = &self.source[self.pos..];
+            let next = [rest.find("{{"), rest.find("{%")].into_iter().flatten().min();
+            let Some(offset) = next else {
+                if !rest.is_empty() {
+                    nodes.push(Node::Text(rest.to_string()));
+                }
+                self.pos = self.source.len();
+                return Ok((nodes, None));
+            };
+            if offset > 0 {
+                nodes.push(Node::Text(rest[..offset].to_string()));
+            }
+            self.pos += offset + 2;
+
+            if rest[offset..].starts_with("{{") {
+                let code = self.read_until("}}")?;
+                nodes.push(Node::Output(parse_code(&code)?));
+                continue;
+            }
+
+            let tag = self.read_until("%}")?;
+            let tag = tag.trim();
+            if let Some(condition) = tag.strip_prefix("if ") {
+                let condition = parse_code(condition)?;
+                nodes.push(self.read_if(condition)?);
+            } else if tag == "else" || tag == "endif" {
+                return Ok((nodes, Some(tag.to_string())));
+            } else {
+                template_fail!("Etiqueta desconocida en la plantilla: {{% {} %}}", tag);
+            }
+        }
+    }
+
+    // Parse para el cuerpo de un {% if %}, con su {% else %} opcional
+    fn read_if(&mut self, condition: Expr) -> Result<Node, ExprError> {
+        let (then_nodes, end) = self.read_nodes()?;
+        let else_nodes = match end.as_deref() {
+            Some("endif") => Vec::new(),
+            Some("else") => match self.read_nodes()? {
+                (else_nodes, Some(tag)) if tag == "endif" => else_nodes,
+                _ => template_fail!("Se esperaba {{% endif %}} después de {{% else %}}"),
+            },
+            _ => template_fail!("Falta {{% endif %}} en la plantilla"),
+        };
+        Ok(Node::If(condition, then_nodes, else_nodes))
+    }
+
+    // Devuelve el código hasta el delimitador de cierre, sin contar los que van dentro de cadenas
+    // ni los que cierran llaves abiertas en el propio código (match x { _ => 1 }}})
+    fn read_until(&mut self, close: &str) -> Result<String, ExprError> {
+        let rest = &self.source[self.pos..];
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut depth = 0;
+        for (i, ch) in rest.char_indices() {
+            if in_string {
+                match ch {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+            } else if ch == '"' {
+                in_string = true;
+            } else if depth == 0 && rest[i..].starts_with(close) {
+                self.pos += i + close.len();
+                return Ok(rest[..i].to_string());
+            } else if ch == '{' {
+                depth += 1;
+            } else if ch == '}' && depth > 0 {
+                depth -= 1;
+            }
+        }
+        template_fail!("Falta '{}' en la plantilla", close);
+    }
+}
+
+// Las expresiones de la plantilla usan el mismo lenguaje que las reglas
+fn parse_code(code: &str) -> Result<Expr, ExprError> {
+    let tokens = try_tokenize(code)?;
+    if tokens.is_empty() {
+        template_fail!("Expresión vacía en la plantilla");
+    }
+    Parser::new(&tokens).try_parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn user_context() -> Context {
+        let mut user = BTreeMap::new();
+        user.insert("name".to_string(), EvalResult::String("Ana".to_string()));
+        user.insert("vip".to_string(), EvalResult::Bool(true));
+        let mut context = Context::new();
+        context.set("user", EvalResult::Map(user));
+        context.set("amount", EvalResult::Int(30));
+        context
+    }
+
+    #[test]
+    fn test_render_expressions() {
+        let mut context = user_context();
+        assert_eq!(
+            render("Hola {{ user.name }}, debes {{ amount * 2 }} €.", &mut context).unwrap(),
+            "Hola Ana, debes 60 €."
+        );
+        // Un "}}" dentro de una cadena no cierra la expresión
+        assert_eq!(render(r#"{{ "}}" == "}}" ? "sí" : "no" }}"#, &mut context).unwrap(), "sí");
+    }
+
+    #[test]
+    fn test_render_if_blocks() {
+        let mut context = user_context();
+        let template = Template::parse(
+            "Hola {{ user.name }}{% if user.vip %}, cliente VIP{% if amount > 100 %} con deuda{% endif %}{% else %}{% endif %}.",
+        )
+        .unwrap();
+        assert_eq!(template.render(&mut context).unwrap(), "Hola Ana, cliente VIP.");
+
+        context.set("amount", EvalResult::Int(150));
+        assert_eq!(template.render(&mut context).unwrap(), "Hola Ana, cliente VIP con deuda.");
+
+        let template = Template::parse("{% if user?.email is not null %}{{ user.email }}{% else %}sin correo{% endif %}").unwrap();
+        assert_eq!(template.render(&mut context).unwrap(), "sin correo");
+    }
+
+    #[test]
+    fn test_render_unclosed_if() {
+        assert_eq!(
+            Template::parse("{% if true %}hola"),
+            Err(ExprError::Syntax("Falta {% endif %} en la plantilla".to_string()))
+        );
+        assert!(Template::parse("{% if x %}a").is_err());
+    }
+
+    #[test]
+    fn test_render_unclosed_expression() {
+        assert_eq!(
+            Template::parse("Hola {{ user.name"),
+            Err(ExprError::Syntax("Falta '}}' en la plantilla".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_are_returned() {
+        let mut context = user_context();
+        assert_eq!(render("Hola {{ 1 + }}", &mut context), Err(ExprError::Syntax("Not valid expresion".to_string())));
+        assert_eq!(render("{{ 1.2.3 }}", &mut context), Err(ExprError::Syntax("Número mal formado: 1.2.3".to_string())));
+        assert_eq!(
+            render("{% foo %}", &mut context),
+            Err(ExprError::Syntax("Etiqueta desconocida en la plantilla: {% foo %}".to_string()))
+        );
+        assert_eq!(render("{{ }}", &mut context), Err(ExprError::Syntax("Expresión vacía en la plantilla".to_string())));
+        assert!(render("{% endif %}", &mut context).is_err());
+    }
+
+    #[test]
+    fn test_render_braces_inside_expression() {
+        let mut context = user_context();
+        assert_eq!(render(r#"{{ match user.name { "Ana" => 1, _ => 0 }}}"#, &mut context).unwrap(), "1");
+        assert_eq!(render("{{ match amount { _ => 1 }}}.", &mut context).unwrap(), "1.");
+    }
+}
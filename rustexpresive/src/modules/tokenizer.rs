@@ -1,7 +1,20 @@
-use std::arch::x86_64;
+#[cfg(feature = "bignum")]
+use crate::modules::bignum;
+#[cfg(feature = "bignum")]
+use num_rational::BigRational;
+use crate::modules::error::ExprError;
+use rust_decimal::Decimal;
+use std::iter::Peekable;
+use std::str::Chars;
 
+// Termina el análisis léxico con un ExprError::Syntax con el mensaje indicado
+macro_rules! lex_fail {
+    ($($arg:tt)*) => {
+        return Err(ExprError::Syntax(format!($($arg)*)))
+    };
+}
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     True,
     False,
@@ -13,14 +26,294 @@ pub enum Token {
     RParen,   // ")"
     And,      // "&&"
     Or,       // "||"
-    Number(f64),  // Para números, tanto enteros como decimales positivos o negativos
+    Number(f64),  // Para números decimales: 3.14 (los negativos se forman en el parser)
+    Integer(i64), // Para números enteros: 42
+    Decimal(Decimal), // Para decimales exactos: 19.99d
+    // Con la característica bignum, los decimales (0.1 es 1/10) y los enteros que no caben en un i64
+    #[cfg(feature = "bignum")]
+    Rational(BigRational),
     Plus,     // "+"
     Minus,    // "-"
     Multiply, // "*"
     Divide,   // "/"
+    Question, // "?"
+    Colon,    // ":"
+    Less,         // "<"
+    LessEqual,    // "<="
+    Greater,      // ">"
+    GreaterEqual, // ">="
+    Identifier(String), // Nombres de variables
+    If,   // "if"
+    Then, // "then"
+    Else, // "else"
+    Case, // "case"
+    When, // "when"
+    End,  // "end"
+    Match,    // "match"
+    LBrace,   // "{"
+    RBrace,   // "}"
+    Comma,    // ","
+    FatArrow, // "=>"
+    Pipe,     // "|"
+    Let,    // "let"
+    In,     // "in"
+    Assign, // "="
+    Semicolon, // ";"
+    Fn, // "fn"
+    Import,  // "import"
+    As,      // "as"
+    PathSep, // "::"
+    Arrow,   // "->"
+    LBracket,   // "["
+    RBracket,   // "]"
+    NotKeyword, // "not" (en "not in")
+    Dot,        // "."
+    Null,     // "null"
+    Is,       // "is"
+    Coalesce, // "??"
+    SafeDot,  // "?."
+    InterpolatedString(Vec<StringPart>), // "Hola ${nombre}"
+    RegexMatch, // "=~"
+    Like,       // "like"
+    ILike,      // "ilike"
+    Glob,       // "glob"
+    CaselessEqual, // "~="
+    Modulo,     // "%"
+    Power,      // "**"
+    IntDivide,  // "//"
+    BitAnd,     // "&" (el "|" de bits es Pipe)
+    BitXor,     // "^"
+    BitNot,     // "~"
+    ShiftLeft,  // "<<"
+    ShiftRight, // ">>"
+}
+
+// Trozos de una cadena con interpolaciones: texto literal o tokens de una expresión ${...}
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Literal(String),
+    Code(Vec<Token>),
+}
+
+// Lee el código de una interpolación hasta la llave que la cierra (ya consumido "${")
+fn read_interpolation(chars: &mut Peekable<Chars>) -> Result<String, ExprError> {
+    let mut code = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if in_string => {
+                code.push(ch);
+                if let Some(escaped) = chars.next() {
+                    code.push(escaped);
+                }
+                continue;
+            }
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => {
+                if depth == 0 {
+                    return Ok(code);
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        code.push(ch);
+    }
+    lex_fail!("Falta '}}' al final de la interpolación");
+}
+
+// Tokens tras los que un "." es un acceso a miembro y no el inicio de un número
+fn ends_value(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token::Identifier(_)) | Some(Token::RParen) | Some(Token::RBracket) | Some(Token::StringLiteral(_))
+    )
+}
+
+// Tokens tras los que un "-" es la resta y no el menos unario
+fn ends_operand(token: Option<&Token>) -> bool {
+    #[cfg(feature = "bignum")]
+    if let Some(Token::Rational(_)) = token {
+        return true;
+    }
+    ends_value(token)
+        || matches!(
+            token,
+            Some(
+                Token::Integer(_) | Token::Number(_) | Token::Decimal(_) | Token::InterpolatedString(_) | Token::True
+                    | Token::False | Token::Null | Token::End | Token::RBrace
+            )
+        )
+}
+
+// Valor absoluto de i64::MIN, que como entero positivo no cabe en un i64
+const I64_MIN_MAGNITUDE: &str = "9223372036854775808";
+
+// Indica si lo que sigue es exactamente el literal 9223372036854775808
+fn is_min_magnitude(chars: &Peekable<Chars>) -> bool {
+    let literal: String = chars.clone().take_while(|&ch| ch.is_alphanumeric() || ch == '_' || ch == '.').collect();
+    literal == I64_MIN_MAGNITUDE
+}
+
+// Lee un literal numérico: 42, 3.14, 1e6, 2.5E-3, 0xFF, 0b1010, 0o17, 1_000_000 y 19.99d
+fn read_number(chars: &mut Peekable<Chars>) -> Result<Token, ExprError> {
+    let mut literal = String::new();
+
+    // Enteros en hexadecimal, binario u octal
+    let mut lookahead = chars.clone();
+    if let (Some('0'), Some(prefix @ ('x' | 'X' | 'b' | 'B' | 'o' | 'O'))) = (lookahead.next(), lookahead.next()) {
+        chars.next();
+        chars.next();
+        take_while(chars, &mut literal, |ch| ch.is_ascii_alphanumeric() || ch == '_');
+        let radix = match prefix.to_ascii_lowercase() {
+            'x' => 16,
+            'b' => 2,
+            _ => 8,
+        };
+        let digits = literal.replace('_', "");
+        let text = format!("0{}{}", prefix, literal);
+        if digits.is_empty() || !digits.chars().all(|ch| ch.is_digit(radix)) {
+            lex_fail!("Número mal formado: {}", text);
+        }
+        return match i64::from_str_radix(&digits, radix) {
+            Ok(integer) => Ok(Token::Integer(integer)),
+            Err(_) => lex_fail!("Entero fuera de rango: {}", text),
+        };
+    }
+
+    // Parte entera, decimales y exponente; "_" separa grupos de dígitos
+    take_while(chars, &mut literal, |ch| ch.is_ascii_digit() || ch == '_');
+    if chars.peek() == Some(&'.') {
+        literal.push('.');
+        chars.next();
+        take_while(chars, &mut literal, |ch| ch.is_ascii_digit() || ch == '_');
+    }
+    let mut lookahead = chars.clone();
+    let exponent = match (lookahead.next(), lookahead.next(), lookahead.next()) {
+        (Some('e' | 'E'), Some(digit), _) if digit.is_ascii_digit() => true,
+        (Some('e' | 'E'), Some('+' | '-'), Some(digit)) if digit.is_ascii_digit() => true,
+        _ => false,
+    };
+    if exponent {
+        literal.extend(chars.next());
+        literal.extend(chars.next());
+        take_while(chars, &mut literal, |ch| ch.is_ascii_digit() || ch == '_');
+    }
+
+    // Sufijo "d": decimal exacto (el "%" de los porcentajes lo resuelve el parser)
+    let mut lookahead = chars.clone();
+    let decimal = match (lookahead.next(), lookahead.next()) {
+        (Some('d'), next) => !next.is_some_and(|ch| ch.is_alphanumeric() || ch == '_'),
+        _ => false,
+    };
+    if decimal {
+        chars.next();
+    }
+
+    // Lo que sigue pegado al número (1.2.3, 12abc, 1e) no puede formar parte de él
+    if chars.peek().is_some_and(|&ch| ch.is_alphanumeric() || ch == '_' || ch == '.') && !decimal {
+        take_while(chars, &mut literal, |ch| ch.is_alphanumeric() || ch == '_' || ch == '.');
+        lex_fail!("Número mal formado: {}", literal);
+    }
+
+    // Los "_" solo pueden ir entre dígitos
+    let text = literal.clone();
+    if literal.ends_with('_') || ["_.", "._", "_e", "_E"].iter().any(|bad| literal.contains(bad)) {
+        lex_fail!("Número mal formado: {}", text);
+    }
+    let number = literal.replace('_', "");
+    let fractional = number.contains(['.', 'e', 'E']);
+    let token = match decimal {
+        true => {
+            let decimal = match exponent {
+                true => Decimal::from_scientific(&number).ok(),
+                false => number.parse().ok(),
+            };
+            match decimal {
+                Some(decimal) => Token::Decimal(decimal),
+                None => lex_fail!("Decimal fuera de rango: {}d", text),
+            }
+        }
+        #[cfg(feature = "bignum")]
+        false if fractional => Token::Rational(parse_rational(&number, &text)?),
+        #[cfg(not(feature = "bignum"))]
+        false if fractional => Token::Number(parse_float(&number, &text)?),
+        false => match number.parse() {
+            Ok(integer) => Token::Integer(integer),
+            #[cfg(feature = "bignum")]
+            Err(_) => Token::Rational(parse_rational(&number, &text)?),
+            #[cfg(not(feature = "bignum"))]
+            Err(_) => lex_fail!("Entero fuera de rango: {}", text),
+        },
+    };
+    Ok(token)
+}
+
+// Añade a "literal" los caracteres que acepta "accept"
+fn take_while(chars: &mut Peekable<impl Iterator<Item = char>>, literal: &mut String, accept: fn(char) -> bool) {
+    while let Some(&ch) = chars.peek() {
+        if !accept(ch) {
+            break;
+        }
+        literal.push(ch);
+        chars.next();
+    }
+}
+
+#[cfg(feature = "bignum")]
+fn parse_rational(number: &str, text: &str) -> Result<BigRational, ExprError> {
+    match bignum::parse_literal(number) {
+        Some(rational) => Ok(rational),
+        None => lex_fail!("Número fuera de rango: {}", text),
+    }
+}
+
+#[cfg(not(feature = "bignum"))]
+fn parse_float(number: &str, text: &str) -> Result<f64, ExprError> {
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => lex_fail!("Número fuera de rango: {}", text),
+        Err(_) => lex_fail!("Número mal formado: {}", text),
+    }
 }
 
+// Token de una palabra reservada; None si la palabra es un identificador
+fn keyword(word: &str) -> Option<Token> {
+    let token = match word {
+        "true" => Token::True,
+        "false" => Token::False,
+        "if" => Token::If,
+        "then" => Token::Then,
+        "else" => Token::Else,
+        "case" => Token::Case,
+        "when" => Token::When,
+        "end" => Token::End,
+        "match" => Token::Match,
+        "let" => Token::Let,
+        "in" => Token::In,
+        "fn" => Token::Fn,
+        "import" => Token::Import,
+        "as" => Token::As,
+        "not" => Token::NotKeyword,
+        "null" => Token::Null,
+        "is" => Token::Is,
+        "like" => Token::Like,
+        "ilike" => Token::ILike,
+        "glob" => Token::Glob,
+        _ => return None,
+    };
+    Some(token)
+}
+
+// Divide el código en tokens; un error léxico hace que se detenga con un panic (usa try_tokenize
+// para recibirlo como un ExprError)
 pub fn tokenize(input: &str) -> Vec<Token> {
+    try_tokenize(input).unwrap_or_else(|err| panic!("{}", err))
+}
+
+pub fn try_tokenize(input: &str) -> Result<Vec<Token>, ExprError> {
     let mut chars = input.chars().peekable();
     let mut tokens = Vec::new();
 
@@ -34,6 +327,15 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 tokens.push(Token::RParen);
                 chars.next();
             }
+            '~' if chars.clone().nth(1) == Some('=') => {
+                chars.next();
+                chars.next();
+                tokens.push(Token::CaselessEqual);
+            }
+            '~' => {
+                tokens.push(Token::BitNot);
+                chars.next();
+            }
             '!' => {
                 chars.next();  
                 if let Some(&'=') = chars.peek() {
@@ -48,8 +350,14 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 if let Some(&'=') = chars.peek() {
                     chars.next();  
                     tokens.push(Token::Equal);
+                } else if let Some(&'>') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::FatArrow);
+                } else if let Some(&'~') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::RegexMatch);
                 } else {
-                    panic!("Error: '=' inesperado");
+                    tokens.push(Token::Assign);
                 }
             }
             '&' => {
@@ -58,7 +366,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     chars.next(); 
                     tokens.push(Token::And); 
                 } else {
-                    panic!("Error: Se esperaba otro '&'");
+                    tokens.push(Token::BitAnd);
                 }
             }
             '|' => {
@@ -67,28 +375,40 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     chars.next(); 
                     tokens.push(Token::Or); 
                 } else {
-                    panic!("Error: Se esperaba otro '|'");
+                    tokens.push(Token::Pipe);
                 }
             }
-            't' => {
-                if chars.clone().take(4).collect::<String>() == "true" {
-                    for _ in 0..4 { chars.next(); }  
-                    tokens.push(Token::True);
-                } else {
-                    panic!("Error: Token inesperado");
+            // Cadena sin escapes r"...": las barras invertidas se quedan tal cual
+            'r' if chars.clone().nth(1) == Some('"') => {
+                chars.next();
+                chars.next();
+                let mut raw = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(ch) => raw.push(ch),
+                        None => lex_fail!("Comilla de cierre no encontrada para la cadena"),
+                    }
                 }
+                tokens.push(Token::StringLiteral(raw));
             }
-            'f' => {
-                if chars.clone().take(5).collect::<String>() == "false" {
-                    for _ in 0..5 { chars.next(); }  
-                    tokens.push(Token::False); 
-                } else {
-                    panic!("Error: Token inesperado");
+            // Palabras: literales booleanos, palabras reservadas e identificadores
+            ch if ch.is_alphabetic() || ch == '_' => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_alphanumeric() || ch == '_' {
+                        word.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
                 }
+                tokens.push(keyword(&word).unwrap_or(Token::Identifier(word)));
             }
             '"' => {
                 // Manejar las cadenas entre comillas, incluyendo comillas escapadas
                 let mut string_literal = String::new();
+                let mut parts = Vec::new();
                 chars.next(); // Consumimos la primera comilla
                 let mut end = false;
                 while let Some(&ch) = chars.peek() {
@@ -96,13 +416,21 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                         chars.next();
                         end = true;
                         break;
+                    } else if ch == '$' && chars.clone().nth(1) == Some('{') {
+                        // Interpolación ${expresión}: se tokeniza aparte
+                        chars.next();
+                        chars.next();
+                        if !string_literal.is_empty() {
+                            parts.push(StringPart::Literal(std::mem::take(&mut string_literal)));
+                        }
+                        parts.push(StringPart::Code(try_tokenize(&read_interpolation(&mut chars)?)?));
                     } else if ch == '\\' {
                         // Si encontramos una barra invertida, verificamos si la siguiente es una comilla
                         chars.next(); 
                         if let Some(&next_ch) = chars.peek() {
-                            if next_ch == '"' {
-                                // Si la siguiente es una comilla, la agregamos a la cadena
-                                string_literal.push('"');
+                            if next_ch == '"' || next_ch == '$' {
+                                // Si la siguiente es una comilla (o un $), la agregamos a la cadena
+                                string_literal.push(next_ch);
                                 chars.next(); // Consumimos la comilla
                             } else {
                                 // Si no es una comilla, agregamos la barra invertida como parte de la cadena
@@ -120,77 +448,161 @@ pub fn tokenize(input: &str) -> Vec<Token> {
 
                 // Si no encontramos una comilla de cierre
                 if !end {
-                    panic!("Error: Comilla de cierre no encontrada para la cadena");
+                    lex_fail!("Comilla de cierre no encontrada para la cadena");
                 }
                 
-                tokens.push(Token::StringLiteral(string_literal));
-            }
-            // Manejar números
-            ch if ch.is_digit(10) || ch == '.' => {
-                // Manejo de números (negativos en el parser)
-                let mut number_str = String::new();
-
-                // Capturamos la parte entera del número
-                while let Some(&ch) = chars.peek() {
-                    if ch.is_digit(10) {
-                        number_str.push(ch);
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
-
-                // Capturamos el punto decimal si existe
-                if let Some(&ch) = chars.peek() {
-                    if ch == '.' {
-                        number_str.push(ch);
-                        chars.next();
-
-                        // Capturamos los dígitos después del punto
-                        while let Some(&ch) = chars.peek() {
-                            if ch.is_digit(10) {
-                                number_str.push(ch);
-                                chars.next();
-                            } else {
-                                break;
-                            }
-                        }
+                if parts.is_empty() {
+                    tokens.push(Token::StringLiteral(string_literal));
+                } else {
+                    if !string_literal.is_empty() {
+                        parts.push(StringPart::Literal(string_literal));
                     }
+                    tokens.push(Token::InterpolatedString(parts));
                 }
-                // Convertimos el string a número (f64)
-                let number: f64 = number_str.parse().unwrap();
-                tokens.push(Token::Number(number));
             }
+            // Punto de acceso a miembros (user.name): tras un valor, o si no le sigue un dígito (.5)
+            '.' if ends_value(tokens.last())
+                || !chars.clone().nth(1).is_some_and(|next| next.is_ascii_digit()) =>
+            {
+                tokens.push(Token::Dot);
+                chars.next();
+            }
+            // Manejar números (los negativos se forman en el parser)
+            ch if ch.is_ascii_digit() || ch == '.' => tokens.push(read_number(&mut chars)?),
             '+' => {
                 tokens.push(Token::Plus);
                 chars.next();
             }
             '-' => {
-                tokens.push(Token::Minus);
                 chars.next();
+                if let Some(&'>') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::Arrow);
+                } else if !ends_operand(tokens.last()) && is_min_magnitude(&chars) {
+                    // i64::MIN solo cabe con su signo: un "-" unario se une a 9223372036854775808
+                    chars.nth(I64_MIN_MAGNITUDE.len() - 1);
+                    tokens.push(Token::Integer(i64::MIN));
+                } else {
+                    tokens.push(Token::Minus);
+                }
             }
             '*' => {
-                tokens.push(Token::Multiply);
                 chars.next();
+                if let Some(&'*') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::Power);
+                } else {
+                    tokens.push(Token::Multiply);
+                }
             }
             '/' => {
-                tokens.push(Token::Divide);
                 chars.next();
+                if let Some(&'/') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::IntDivide);
+                } else {
+                    tokens.push(Token::Divide);
+                }
+            }
+            '%' => {
+                tokens.push(Token::Modulo);
+                chars.next();
+            }
+            '^' => {
+                tokens.push(Token::BitXor);
+                chars.next();
+            }
+            '<' => {
+                chars.next();
+                match chars.peek() {
+                    Some(&'=') => {
+                        chars.next();
+                        tokens.push(Token::LessEqual);
+                    }
+                    Some(&'<') => {
+                        chars.next();
+                        tokens.push(Token::ShiftLeft);
+                    }
+                    _ => tokens.push(Token::Less),
+                }
+            }
+            '>' => {
+                chars.next();
+                match chars.peek() {
+                    Some(&'=') => {
+                        chars.next();
+                        tokens.push(Token::GreaterEqual);
+                    }
+                    Some(&'>') => {
+                        chars.next();
+                        tokens.push(Token::ShiftRight);
+                    }
+                    _ => tokens.push(Token::Greater),
+                }
+            }
+            '{' => {
+                tokens.push(Token::LBrace);
+                chars.next();
+            }
+            '}' => {
+                tokens.push(Token::RBrace);
+                chars.next();
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                chars.next();
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                chars.next();
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                chars.next();
+            }
+            ';' => {
+                tokens.push(Token::Semicolon);
+                chars.next();
+            }
+            '?' => {
+                chars.next();
+                let mut lookahead = chars.clone();
+                match (lookahead.next(), lookahead.next()) {
+                    (Some('?'), _) => {
+                        chars.next();
+                        tokens.push(Token::Coalesce);
+                    }
+                    // "?.5" es un ternario seguido de un número, no una navegación segura
+                    (Some('.'), next) if !next.is_some_and(|next| next.is_ascii_digit()) => {
+                        chars.next();
+                        tokens.push(Token::SafeDot);
+                    }
+                    _ => tokens.push(Token::Question),
+                }
+            }
+            ':' => {
+                chars.next();
+                if let Some(&':') = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::PathSep);
+                } else {
+                    tokens.push(Token::Colon);
+                }
             }
 
-            // /////// Ignorar espacios en blanco
-            ' ' => { chars.next(); } 
-            _ => panic!("Error: Caracter inesperado '{}'", ch), 
+            // /////// Ignorar espacios en blanco (incluidos saltos de línea y tabuladores)
+            ch if ch.is_whitespace() => { chars.next(); } 
+            _ => lex_fail!("Caracter inesperado '{}'", ch), 
         }
     }
 
-    tokens
+    Ok(tokens)
 }
 
 
-///////////////
-/// TEST ⚠️///
-///////////////
+// ////////////
+// TEST ⚠️ ///
+// ////////////
 
 #[cfg(test)]
 mod tests {
@@ -277,28 +689,320 @@ mod tests {
     // NUMEROS
     #[test]
     fn test_tokenize_numbers() {
-        assert_eq!(tokenize("123"), vec![Token::Number(123.0)]);
-        assert_eq!(tokenize("-123"), vec![Token::Minus, Token::Number(123.0)]);
+        assert_eq!(tokenize("123"), vec![Token::Integer(123)]);
+        assert_eq!(tokenize("-123"), vec![Token::Minus, Token::Integer(123)]);
+        #[cfg(not(feature = "bignum"))]
         assert_eq!(tokenize("123.45"), vec![Token::Number(123.45)]);
+        #[cfg(not(feature = "bignum"))]
         assert_eq!(tokenize("0.1"), vec![Token::Number(0.1)]);
+        #[cfg(not(feature = "bignum"))]
         assert_eq!(tokenize(".5"), vec![Token::Number(0.5)]);
     }
 
     #[test]
     fn test_tokenize_operations() {
-        assert_eq!(tokenize("-1 + 2"), vec![Token::Minus, Token::Number(1.0), Token::Plus, Token::Number(2.0)]);
-        assert_eq!(tokenize("3 - 4"), vec![Token::Number(3.0), Token::Minus, Token::Number(4.0)]);
-        assert_eq!(tokenize("5 * 6"), vec![Token::Number(5.0), Token::Multiply, Token::Number(6.0)]);
-        assert_eq!(tokenize("7 / 8"), vec![Token::Number(7.0), Token::Divide, Token::Number(8.0)]);
+        assert_eq!(tokenize("-1 + 2"), vec![Token::Minus, Token::Integer(1), Token::Plus, Token::Integer(2)]);
+        assert_eq!(tokenize("3 - 4"), vec![Token::Integer(3), Token::Minus, Token::Integer(4)]);
+        assert_eq!(tokenize("5 * 6"), vec![Token::Integer(5), Token::Multiply, Token::Integer(6)]);
+        assert_eq!(tokenize("7 / 8"), vec![Token::Integer(7), Token::Divide, Token::Integer(8)]);
     }
 
     #[test]
     fn test_tokenize_number_complex_expression() {
         let result = tokenize("1 + 2 * 3 - 4 / 5");
         assert_eq!(result, vec![
-            Token::Number(1.0), Token::Plus, 
-            Token::Number(2.0), Token::Multiply, Token::Number(3.0), 
-            Token::Minus, Token::Number(4.0), Token::Divide, Token::Number(5.0)
+            Token::Integer(1), Token::Plus, 
+            Token::Integer(2), Token::Multiply, Token::Integer(3), 
+            Token::Minus, Token::Integer(4), Token::Divide, Token::Integer(5)
+        ]);
+    }
+
+    // TERNARIO
+    #[test]
+    fn test_tokenize_ternary() {
+        assert_eq!(tokenize(r#"true ? "a" : "b""#), vec![
+            Token::True, Token::Question,
+            Token::StringLiteral("a".to_string()), Token::Colon,
+            Token::StringLiteral("b".to_string())
+        ]);
+    }
+
+    // COMPARACIONES
+    #[test]
+    fn test_tokenize_comparisons() {
+        assert_eq!(tokenize("1 < 2 <= 3 > 4 >= 5"), vec![
+            Token::Integer(1), Token::Less, Token::Integer(2), Token::LessEqual,
+            Token::Integer(3), Token::Greater, Token::Integer(4), Token::GreaterEqual,
+            Token::Integer(5)
+        ]);
+    }
+
+    // PALABRAS RESERVADAS E IDENTIFICADORES
+    #[test]
+    fn test_tokenize_keywords_and_identifiers() {
+        assert_eq!(tokenize("if x then true else false"), vec![
+            Token::If, Token::Identifier("x".to_string()), Token::Then,
+            Token::True, Token::Else, Token::False
+        ]);
+        assert_eq!(tokenize("case when total_2 > 10 then 1 end"), vec![
+            Token::Case, Token::When, Token::Identifier("total_2".to_string()),
+            Token::Greater, Token::Integer(10), Token::Then, Token::Integer(1), Token::End
+        ]);
+        assert_eq!(tokenize("trueish"), vec![Token::Identifier("trueish".to_string())]);
+    }
+
+    // MATCH
+    #[test]
+    fn test_tokenize_match() {
+        assert_eq!(tokenize(r#"match s { "a" | "b" => 1, _ => 0 }"#), vec![
+            Token::Match, Token::Identifier("s".to_string()), Token::LBrace,
+            Token::StringLiteral("a".to_string()), Token::Pipe, Token::StringLiteral("b".to_string()),
+            Token::FatArrow, Token::Integer(1), Token::Comma,
+            Token::Identifier("_".to_string()), Token::FatArrow, Token::Integer(0), Token::RBrace
+        ]);
+    }
+
+    // LET
+    #[test]
+    fn test_tokenize_let() {
+        assert_eq!(tokenize("let x = 1 in x == 1"), vec![
+            Token::Let, Token::Identifier("x".to_string()), Token::Assign, Token::Integer(1),
+            Token::In, Token::Identifier("x".to_string()), Token::Equal, Token::Integer(1)
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_statements() {
+        assert_eq!(tokenize("x = 1; x"), vec![
+            Token::Identifier("x".to_string()), Token::Assign, Token::Integer(1),
+            Token::Semicolon, Token::Identifier("x".to_string())
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_function_definition() {
+        assert_eq!(tokenize("fn f(a, b) = a"), vec![
+            Token::Fn, Token::Identifier("f".to_string()), Token::LParen,
+            Token::Identifier("a".to_string()), Token::Comma, Token::Identifier("b".to_string()),
+            Token::RParen, Token::Assign, Token::Identifier("a".to_string())
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_lambda() {
+        assert_eq!(tokenize("x -> x-1"), vec![
+            Token::Identifier("x".to_string()), Token::Arrow,
+            Token::Identifier("x".to_string()), Token::Minus, Token::Integer(1)
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_lists_and_membership() {
+        assert_eq!(tokenize(r#"c not in ["ES", l[0]]"#), vec![
+            Token::Identifier("c".to_string()), Token::NotKeyword, Token::In, Token::LBracket,
+            Token::StringLiteral("ES".to_string()), Token::Comma,
+            Token::Identifier("l".to_string()), Token::LBracket, Token::Integer(0), Token::RBracket,
+            Token::RBracket
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_member_access() {
+        assert_eq!(tokenize(r#"user.address.city == attrs["x"].v"#), vec![
+            Token::Identifier("user".to_string()), Token::Dot, Token::Identifier("address".to_string()),
+            Token::Dot, Token::Identifier("city".to_string()), Token::Equal,
+            Token::Identifier("attrs".to_string()), Token::LBracket, Token::StringLiteral("x".to_string()),
+            Token::RBracket, Token::Dot, Token::Identifier("v".to_string())
+        ]);
+        // Un punto seguido de un dígito, sin un valor delante, sigue siendo un número
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(tokenize("a * .5"), vec![
+            Token::Identifier("a".to_string()), Token::Multiply, Token::Number(0.5)
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_null_operators() {
+        assert_eq!(tokenize("a?.b ?? null is not null"), vec![
+            Token::Identifier("a".to_string()), Token::SafeDot, Token::Identifier("b".to_string()),
+            Token::Coalesce, Token::Null, Token::Is, Token::NotKeyword, Token::Null
+        ]);
+        #[cfg(not(feature = "bignum"))]
+        assert_eq!(tokenize("c ?.5 : 1"), vec![
+            Token::Identifier("c".to_string()), Token::Question, Token::Number(0.5),
+            Token::Colon, Token::Integer(1)
+        ]);
+    }
+
+    // EXPRESIONES REGULARES
+    #[test]
+    fn test_tokenize_regex_match_and_raw_string() {
+        assert_eq!(tokenize(r#"email =~ r"^\w+@corp\.com$""#), vec![
+            Token::Identifier("email".to_string()),
+            Token::RegexMatch,
+            Token::StringLiteral(r"^\w+@corp\.com$".to_string()),
+        ]);
+        // Una "r" seguida de otra cosa sigue siendo un identificador
+        assert_eq!(tokenize("r == ru"), vec![
+            Token::Identifier("r".to_string()),
+            Token::Equal,
+            Token::Identifier("ru".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_wildcard_operators() {
+        assert_eq!(tokenize(r#"name like "Jo%" && path not glob "*.log""#), vec![
+            Token::Identifier("name".to_string()),
+            Token::Like,
+            Token::StringLiteral("Jo%".to_string()),
+            Token::And,
+            Token::Identifier("path".to_string()),
+            Token::NotKeyword,
+            Token::Glob,
+            Token::StringLiteral("*.log".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_caseless_equal() {
+        assert_eq!(tokenize(r#"pais ~= "españa""#), vec![
+            Token::Identifier("pais".to_string()),
+            Token::CaselessEqual,
+            Token::StringLiteral("españa".to_string()),
+        ]);
+    }
+
+    // INTERPOLACIÓN
+    #[test]
+    fn test_tokenize_interpolated_string() {
+        assert_eq!(tokenize(r#""Hola ${user.name}, debes ${amount}""#), vec![
+            Token::InterpolatedString(vec![
+                StringPart::Literal("Hola ".to_string()),
+                StringPart::Code(vec![
+                    Token::Identifier("user".to_string()), Token::Dot, Token::Identifier("name".to_string())
+                ]),
+                StringPart::Literal(", debes ".to_string()),
+                StringPart::Code(vec![Token::Identifier("amount".to_string())]),
+            ])
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_interpolation_with_nested_braces_and_strings() {
+        assert_eq!(tokenize(r#""${match s { "}" => 1, _ => 2 }}""#), vec![
+            Token::InterpolatedString(vec![StringPart::Code(tokenize(r#"match s { "}" => 1, _ => 2 }"#))])
+        ]);
+        // Con \$ el texto se queda tal cual
+        assert_eq!(tokenize(r#""precio: \${x}""#), vec![Token::StringLiteral("precio: ${x}".to_string())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Falta '}' al final de la interpolación")]
+    fn test_tokenize_unclosed_interpolation() {
+        tokenize(r#""Hola ${nombre""#);
+    }
+
+    #[test]
+    fn test_tokenize_large_integers() {
+        assert_eq!(tokenize("9007199254740993"), vec![Token::Integer(9007199254740993)]);
+        assert_eq!(tokenize("9223372036854775807"), vec![Token::Integer(i64::MAX)]);
+        // i64::MIN se escribe con el menos unario pegado; tras un operando el "-" sigue siendo la resta
+        assert_eq!(tokenize("-9223372036854775808"), vec![Token::Integer(i64::MIN)]);
+        assert_eq!(tokenize("[1, -9223372036854775808]"), vec![
+            Token::LBracket, Token::Integer(1), Token::Comma, Token::Integer(i64::MIN), Token::RBracket
+        ]);
+        assert_eq!(tokenize("x * -9223372036854775808"), vec![
+            Token::Identifier("x".to_string()), Token::Multiply, Token::Integer(i64::MIN)
+        ]);
+        assert_eq!(tokenize("-9223372036854775808d")[0], Token::Minus);
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    #[should_panic(expected = "Error: Entero fuera de rango: 9223372036854775808")]
+    fn test_tokenize_subtract_integer_out_of_range() {
+        tokenize("x -9223372036854775808");
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    #[should_panic(expected = "Error: Entero fuera de rango: 9223372036854775808")]
+    fn test_tokenize_integer_out_of_range() {
+        tokenize("9223372036854775808");
+    }
+
+    #[test]
+    fn test_tokenize_decimal_literals() {
+        assert_eq!(tokenize("19.99d"), vec![Token::Decimal(Decimal::new(1999, 2))]);
+        assert_eq!(tokenize("5d + .5d"), vec![Token::Decimal(Decimal::new(5, 0)), Token::Plus, Token::Decimal(Decimal::new(5, 1))]);
+        // Una "d" que empieza un identificador no es el sufijo
+        assert_eq!(tokenize("5 days"), vec![Token::Integer(5), Token::Identifier("days".to_string())]);
+    }
+
+    #[test]
+    fn test_tokenize_number_formats() {
+        assert_eq!(tokenize("0xFF 0b1010 0o17 0x_ff_ff"), vec![
+            Token::Integer(255), Token::Integer(10), Token::Integer(15), Token::Integer(65535)
+        ]);
+        assert_eq!(tokenize("1_000_000"), vec![Token::Integer(1_000_000)]);
+        assert_eq!(tokenize("1.5e3d"), vec![Token::Decimal(Decimal::new(1500, 0))]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "bignum"))]
+    fn test_tokenize_exponents() {
+        assert_eq!(tokenize("1e6"), vec![Token::Number(1e6)]);
+        assert_eq!(tokenize("2.5E-3 + 1e+2"), vec![Token::Number(2.5e-3), Token::Plus, Token::Number(100.0)]);
+    }
+
+    #[test]
+    fn test_try_tokenize_returns_errors() {
+        assert_eq!(try_tokenize("1.2.3"), Err(ExprError::Syntax("Número mal formado: 1.2.3".to_string())));
+        assert_eq!(try_tokenize("a # b"), Err(ExprError::Syntax("Caracter inesperado '#'".to_string())));
+        assert_eq!(try_tokenize("1 + 2").unwrap(), vec![Token::Integer(1), Token::Plus, Token::Integer(2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error: Número mal formado: 1.2.3")]
+    fn test_tokenize_malformed_number() {
+        tokenize("1.2.3");
+    }
+
+    #[test]
+    fn test_tokenize_malformed_numbers() {
+        for input in ["12abc", "1e", "0x", "0b102", "1__", "1_.5", "0xfg"] {
+            let result = std::panic::catch_unwind(|| tokenize(input));
+            assert!(result.is_err(), "{} debería ser un error", input);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_extended_operators() {
+        assert_eq!(tokenize("a % 2 ** 3 // 4"), vec![
+            Token::Identifier("a".to_string()), Token::Modulo, Token::Integer(2), Token::Power,
+            Token::Integer(3), Token::IntDivide, Token::Integer(4)
+        ]);
+        assert_eq!(tokenize("a & b | c ^ ~d << 1 >> 2 && e ~= f"), vec![
+            Token::Identifier("a".to_string()), Token::BitAnd, Token::Identifier("b".to_string()), Token::Pipe,
+            Token::Identifier("c".to_string()), Token::BitXor, Token::BitNot, Token::Identifier("d".to_string()),
+            Token::ShiftLeft, Token::Integer(1), Token::ShiftRight, Token::Integer(2), Token::And,
+            Token::Identifier("e".to_string()), Token::CaselessEqual, Token::Identifier("f".to_string())
+        ]);
+        // "%" es siempre un token propio, vaya pegado al número o no (el porcentaje lo resuelve el parser)
+        assert_eq!(tokenize("15%"), vec![Token::Integer(15), Token::Modulo]);
+        assert_eq!(tokenize("7%-2"), vec![Token::Integer(7), Token::Modulo, Token::Minus, Token::Integer(2)]);
+        assert_eq!(tokenize("7 % -2"), vec![Token::Integer(7), Token::Modulo, Token::Minus, Token::Integer(2)]);
+        assert_eq!(tokenize("12.5d%"), vec![Token::Decimal(Decimal::new(125, 1)), Token::Modulo]);
+    }
+
+    #[test]
+    fn test_tokenize_import_and_namespaces() {
+        assert_eq!(tokenize("import \"common/geo.rex\" as g;\n\tg::sq(2)"), vec![
+            Token::Import, Token::StringLiteral("common/geo.rex".to_string()), Token::As,
+            Token::Identifier("g".to_string()), Token::Semicolon,
+            Token::Identifier("g".to_string()), Token::PathSep, Token::Identifier("sq".to_string()),
+            Token::LParen, Token::Integer(2), Token::RParen
         ]);
     }
 